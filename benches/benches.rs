@@ -1,4 +1,4 @@
-use criterion::{BenchmarkId, Criterion};
+use criterion::{BatchSize, BenchmarkId, Criterion};
 
 #[derive(Default)]
 struct Small(usize);
@@ -6,6 +6,17 @@ struct Small(usize);
 #[derive(Default)]
 struct Big([usize; 32]);
 
+// The same as `Small`, but with a destructor, so the arena has to drop each
+// value.
+#[derive(Default)]
+struct SmallDrop(usize);
+
+impl Drop for SmallDrop {
+    fn drop(&mut self) {
+        criterion::black_box(self.0);
+    }
+}
+
 fn allocate<T: Default>(n: usize) {
     let arena = typed_arena::Arena::new();
     for _ in 0..n {
@@ -14,6 +25,12 @@ fn allocate<T: Default>(n: usize) {
     }
 }
 
+fn filled<T: Default>(n: usize) -> typed_arena::Arena<T> {
+    let arena = typed_arena::Arena::new();
+    arena.alloc_extend((0..n).map(|_| T::default()));
+    arena
+}
+
 fn criterion_benchmark(c: &mut Criterion) {
     let mut group = c.benchmark_group("allocate");
     for n in 1..5 {
@@ -26,6 +43,41 @@ fn criterion_benchmark(c: &mut Criterion) {
             b.iter(|| allocate::<Big>(n))
         });
     }
+    group.finish();
+
+    let mut group = c.benchmark_group("drop");
+    for n in 1..5 {
+        let n = n * 10_000;
+        group.throughput(criterion::Throughput::Elements(n as u64));
+        group.bench_with_input(BenchmarkId::new("drop-dropless", n), &n, |b, &n| {
+            b.iter_batched(|| filled::<Small>(n), drop, BatchSize::LargeInput)
+        });
+        group.bench_with_input(BenchmarkId::new("drop-needs-drop", n), &n, |b, &n| {
+            b.iter_batched(|| filled::<SmallDrop>(n), drop, BatchSize::LargeInput)
+        });
+    }
+    group.finish();
+
+    let mut group = c.benchmark_group("clear");
+    for n in 1..5 {
+        let n = n * 10_000;
+        group.throughput(criterion::Throughput::Elements(n as u64));
+        group.bench_with_input(BenchmarkId::new("clear-dropless", n), &n, |b, &n| {
+            b.iter_batched_ref(
+                || filled::<Small>(n),
+                |arena| arena.clear(),
+                BatchSize::LargeInput,
+            )
+        });
+        group.bench_with_input(BenchmarkId::new("clear-needs-drop", n), &n, |b, &n| {
+            b.iter_batched_ref(
+                || filled::<SmallDrop>(n),
+                |arena| arena.clear(),
+                BatchSize::LargeInput,
+            )
+        });
+    }
+    group.finish();
 }
 
 criterion::criterion_group!(benches, criterion_benchmark);
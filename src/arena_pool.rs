@@ -0,0 +1,164 @@
+//! Arenas that are reused between units of work.
+
+use std::cmp;
+use std::fmt;
+use std::ops::{Deref, DerefMut};
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+
+use super::Arena;
+
+/// A pool of arenas, for handing out one arena per request.
+///
+/// [`get`](ArenaPool::get) returns a [`PooledArena`], which derefs to an
+/// [`Arena`] and is cleared and returned to the pool when it is dropped.
+/// Cloning an `ArenaPool` creates another handle to the same pool.
+///
+/// The pool keeps track of the most values recently allocated in one of its
+/// arenas. New arenas are created with that capacity, and returned arenas
+/// that are too small or much too big for it are dropped instead of being
+/// kept, so arenas from the pool usually have enough room without
+/// allocating. The peak decays a little every time an arena is returned, so
+/// the pool adapts when requests get smaller. At most `max_warm` arenas are
+/// kept.
+///
+/// Since a `PooledArena` clears its arena when it is dropped, the values in
+/// it can't hold references to each other.
+///
+/// ## Example
+///
+/// ```
+/// use typed_arena::ArenaPool;
+///
+/// let pool: ArenaPool<String> = ArenaPool::new(4);
+/// for request in 0..10 {
+///     let arena = pool.get();
+///     for i in 0..request {
+///         arena.alloc(format!("value {}", i));
+///     }
+/// }
+/// assert_eq!(pool.warm_arenas(), 1);
+/// ```
+pub struct ArenaPool<T> {
+    inner: Arc<Mutex<Inner<T>>>,
+}
+
+struct Inner<T> {
+    warm: Vec<Arena<T>>,
+    max_warm: usize,
+    peak: usize,
+}
+
+impl<T> ArenaPool<T> {
+    /// Create an empty pool that keeps at most `max_warm` arenas.
+    pub fn new(max_warm: usize) -> ArenaPool<T> {
+        ArenaPool {
+            inner: Arc::new(Mutex::new(Inner {
+                warm: Vec::new(),
+                max_warm,
+                peak: 0,
+            })),
+        }
+    }
+
+    /// Take an arena from the pool, or create one if the pool is empty.
+    ///
+    /// The arena is empty, and is returned to the pool when the
+    /// `PooledArena` is dropped.
+    pub fn get(&self) -> PooledArena<T> {
+        let mut inner = self.lock();
+        let arena = match inner.warm.pop() {
+            Some(arena) => arena,
+            None if inner.peak == 0 => Arena::new(),
+            None => Arena::with_capacity(inner.peak),
+        };
+        PooledArena {
+            arena: Some(arena),
+            pool: self.clone(),
+        }
+    }
+
+    /// The number of arenas in the pool, ready to be handed out.
+    pub fn warm_arenas(&self) -> usize {
+        self.lock().warm.len()
+    }
+
+    /// The recent peak number of values in an arena from this pool.
+    pub fn peak_len(&self) -> usize {
+        self.lock().peak
+    }
+
+    fn put(&self, mut arena: Arena<T>) {
+        let len = arena.len();
+        arena.clear();
+        let chunks = arena.chunks.inner.get_mut();
+        // The next user of the arena starts with one it can grow.
+        chunks.locked = false;
+        let capacity = chunks.capacity;
+
+        let mut inner = self.lock();
+        let peak = cmp::max(len, inner.peak.saturating_sub(inner.peak / 16 + 1));
+        inner.peak = peak;
+        let fits = capacity >= peak && capacity / 4 <= peak;
+        if fits && inner.warm.len() < inner.max_warm {
+            inner.warm.push(arena);
+        }
+    }
+
+    // A panic while the lock is held can't leave the pool inconsistent, so
+    // poisoning is ignored.
+    fn lock(&self) -> MutexGuard<'_, Inner<T>> {
+        self.inner.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+impl<T> Clone for ArenaPool<T> {
+    fn clone(&self) -> Self {
+        ArenaPool {
+            inner: self.inner.clone(),
+        }
+    }
+}
+
+impl<T> fmt::Debug for ArenaPool<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let inner = self.lock();
+        f.debug_struct("ArenaPool")
+            .field("warm_arenas", &inner.warm.len())
+            .field("max_warm", &inner.max_warm)
+            .field("peak_len", &inner.peak)
+            .finish()
+    }
+}
+
+/// An arena borrowed from an [`ArenaPool`].
+///
+/// This derefs to the [`Arena`], and clears it and returns it to the pool when
+/// dropped. A [locked](Arena::lock_capacity) arena is unlocked when it is
+/// returned.
+pub struct PooledArena<T> {
+    // Only `None` while being dropped.
+    arena: Option<Arena<T>>,
+    pool: ArenaPool<T>,
+}
+
+impl<T> Deref for PooledArena<T> {
+    type Target = Arena<T>;
+
+    fn deref(&self) -> &Arena<T> {
+        self.arena.as_ref().unwrap()
+    }
+}
+
+impl<T> DerefMut for PooledArena<T> {
+    fn deref_mut(&mut self) -> &mut Arena<T> {
+        self.arena.as_mut().unwrap()
+    }
+}
+
+impl<T> Drop for PooledArena<T> {
+    fn drop(&mut self) {
+        if let Some(arena) = self.arena.take() {
+            self.pool.put(arena);
+        }
+    }
+}
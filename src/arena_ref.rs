@@ -0,0 +1,136 @@
+//! References that compare by address.
+
+use core::borrow::Borrow;
+use core::cmp::Ordering;
+use core::fmt;
+use core::hash::{Hash, Hasher};
+use core::ops::Deref;
+
+/// A shared reference to a value in an arena that is compared, hashed and
+/// ordered by its address instead of by the value.
+///
+/// Values in an arena never move, so their address identifies them for as
+/// long as they are borrowed. That makes `ArenaRef` a good key for the nodes
+/// of a graph in a `HashMap` or `BTreeMap`, even if two nodes are equal or
+/// `T` doesn't implement `Eq` at all. Note that all values of a zero-sized
+/// type, and all empty slices, may share the same address.
+///
+/// An `ArenaRef` is created from the `&mut T` or `&T` an arena returns, and
+/// derefs to `T`.
+///
+/// ## Example
+///
+/// ```
+/// use std::collections::HashSet;
+/// use typed_arena::{Arena, ArenaRef};
+///
+/// let arena = Arena::new();
+/// let a = ArenaRef::from(arena.alloc("node"));
+/// let b = ArenaRef::from(arena.alloc("node"));
+/// assert_eq!(*a, *b);
+/// assert_ne!(a, b);
+///
+/// let visited: HashSet<_> = [a, b, a].iter().copied().collect();
+/// assert_eq!(visited.len(), 2);
+/// ```
+pub struct ArenaRef<'a, T: ?Sized> {
+    value: &'a T,
+}
+
+impl<'a, T: ?Sized> ArenaRef<'a, T> {
+    /// Wrap a reference to a value in an arena.
+    pub fn new(value: &'a T) -> ArenaRef<'a, T> {
+        ArenaRef { value }
+    }
+
+    /// Return the reference this wraps.
+    pub fn get(self) -> &'a T {
+        self.value
+    }
+
+    /// Return the address of the value.
+    pub fn as_ptr(self) -> *const T {
+        self.value
+    }
+
+    // Only the address is compared, not the length of a slice or the vtable
+    // of a trait object.
+    fn addr(self) -> *const () {
+        self.as_ptr().cast()
+    }
+}
+
+impl<'a, T: ?Sized> Clone for ArenaRef<'a, T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<'a, T: ?Sized> Copy for ArenaRef<'a, T> {}
+
+impl<'a, T: ?Sized> Deref for ArenaRef<'a, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        self.value
+    }
+}
+
+impl<'a, T: ?Sized> Borrow<T> for ArenaRef<'a, T> {
+    fn borrow(&self) -> &T {
+        self.value
+    }
+}
+
+impl<'a, T: ?Sized> AsRef<T> for ArenaRef<'a, T> {
+    fn as_ref(&self) -> &T {
+        self.value
+    }
+}
+
+impl<'a, T: ?Sized> From<&'a T> for ArenaRef<'a, T> {
+    fn from(value: &'a T) -> Self {
+        ArenaRef::new(value)
+    }
+}
+
+impl<'a, T: ?Sized> From<&'a mut T> for ArenaRef<'a, T> {
+    fn from(value: &'a mut T) -> Self {
+        ArenaRef::new(value)
+    }
+}
+
+impl<'a, T: ?Sized> PartialEq for ArenaRef<'a, T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.addr() == other.addr()
+    }
+}
+
+impl<'a, T: ?Sized> Eq for ArenaRef<'a, T> {}
+
+impl<'a, T: ?Sized> PartialOrd for ArenaRef<'a, T> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<'a, T: ?Sized> Ord for ArenaRef<'a, T> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.addr().cmp(&other.addr())
+    }
+}
+
+impl<'a, T: ?Sized> Hash for ArenaRef<'a, T> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.addr().hash(state);
+    }
+}
+
+impl<'a, T: ?Sized + fmt::Debug> fmt::Debug for ArenaRef<'a, T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_tuple("ArenaRef")
+            .field(&self.as_ptr())
+            .field(&self.value)
+            .finish()
+    }
+}
@@ -0,0 +1,160 @@
+//! Arenas whose ids can't be used with any other arena.
+
+use core::fmt;
+use core::hash::{Hash, Hasher};
+use core::marker::PhantomData;
+use core::ops::Deref;
+
+use super::Arena;
+
+// Invariant in `'brand`, so that two brands can never be unified.
+type Brand<'brand> = PhantomData<fn(&'brand ()) -> &'brand ()>;
+
+/// An arena whose [`BrandedId`]s can only be used with it.
+///
+/// Created by [`Arena::with_brand`], which gives every `BrandedArena` a
+/// lifetime `'brand` of its own. Ids carry that brand, so the compiler
+/// rejects looking one up in any other arena, and since a `BrandedArena` can
+/// never be cleared, [`get`](BrandedArena::get) doesn't need to check
+/// anything and can't fail.
+///
+/// A `BrandedArena` derefs to the [`Arena`] inside it, so it can also
+/// allocate values the usual way.
+///
+/// ## Example
+///
+/// ```
+/// use typed_arena::Arena;
+///
+/// let sum = Arena::with_brand(|arena| {
+///     let ids: Vec<_> = (1..=10).map(|i| arena.alloc_id(i)).collect();
+///     ids.iter().map(|&id| arena.get(id)).sum::<u32>()
+/// });
+/// assert_eq!(sum, 55);
+/// ```
+///
+/// Ids from one arena can't be used with another:
+///
+/// ```compile_fail
+/// use typed_arena::Arena;
+///
+/// Arena::with_brand(|a| {
+///     Arena::with_brand(|b| {
+///         let id = a.alloc_id(1);
+///         b.alloc_id(2);
+///         b.get(id);
+///     });
+/// });
+/// ```
+pub struct BrandedArena<'brand, T> {
+    arena: Arena<T>,
+    _brand: Brand<'brand>,
+}
+
+impl<'brand, T> BrandedArena<'brand, T> {
+    pub(crate) fn new(arena: Arena<T>) -> BrandedArena<'brand, T> {
+        BrandedArena {
+            arena,
+            _brand: PhantomData,
+        }
+    }
+
+    /// Allocates a value in the arena, and returns a [`BrandedId`] for it.
+    ///
+    /// ## Panics
+    ///
+    /// Panics if the arena holds more than `u32::MAX` values.
+    #[cfg_attr(feature = "debug-callsites", track_caller)]
+    pub fn alloc_id(&self, value: T) -> BrandedId<'brand, T> {
+        let index = self.arena.len();
+        assert!(
+            index <= u32::MAX as usize,
+            "too many values in the arena for an id"
+        );
+        self.arena.alloc(value);
+        BrandedId {
+            index: index as u32,
+            _brand: PhantomData,
+            _marker: PhantomData,
+        }
+    }
+
+    /// Returns a reference to the value `id` refers to.
+    pub fn get(&self, id: BrandedId<'brand, T>) -> &T {
+        // The brand guarantees that `id` came from this arena, which still
+        // holds every value it ever allocated. Those allocated with
+        // `alloc_id` are only reachable through ids.
+        unsafe { &*self.arena.slot_unchecked(id.index()) }
+    }
+
+    /// Returns a mutable reference to the value `id` refers to.
+    pub fn get_mut(&mut self, id: BrandedId<'brand, T>) -> &mut T {
+        unsafe { &mut *self.arena.slot_unchecked(id.index()) }
+    }
+
+    /// Unwrap the [`Arena`] inside, which can be used outside the closure
+    /// passed to [`Arena::with_brand`].
+    pub fn into_inner(self) -> Arena<T> {
+        self.arena
+    }
+}
+
+impl<'brand, T> Deref for BrandedArena<'brand, T> {
+    type Target = Arena<T>;
+
+    fn deref(&self) -> &Arena<T> {
+        &self.arena
+    }
+}
+
+impl<'brand, T> fmt::Debug for BrandedArena<'brand, T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_tuple("BrandedArena").field(&self.arena).finish()
+    }
+}
+
+/// A handle to a value allocated with
+/// [`BrandedArena::alloc_id`](BrandedArena::alloc_id).
+///
+/// Like an [`Id`](crate::Id), this is a `Copy` index, but it is only 32 bits
+/// and can only be used with the arena that created it.
+pub struct BrandedId<'brand, T> {
+    index: u32,
+    _brand: Brand<'brand>,
+    _marker: PhantomData<fn() -> T>,
+}
+
+impl<'brand, T> BrandedId<'brand, T> {
+    /// The index of the value in its arena.
+    pub fn index(self) -> usize {
+        self.index as usize
+    }
+}
+
+impl<'brand, T> Clone for BrandedId<'brand, T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<'brand, T> Copy for BrandedId<'brand, T> {}
+
+impl<'brand, T> PartialEq for BrandedId<'brand, T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.index == other.index
+    }
+}
+
+impl<'brand, T> Eq for BrandedId<'brand, T> {}
+
+impl<'brand, T> Hash for BrandedId<'brand, T> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.index.hash(state);
+    }
+}
+
+impl<'brand, T> fmt::Debug for BrandedId<'brand, T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_tuple("BrandedId").field(&self.index).finish()
+    }
+}
@@ -0,0 +1,100 @@
+//! Memory limits shared between arenas.
+
+#[cfg(not(feature = "std"))]
+use alloc::sync::Arc;
+#[cfg(feature = "std")]
+use std::sync::Arc;
+
+use core::fmt;
+use core::sync::atomic::{AtomicUsize, Ordering};
+
+/// A limit on the memory that a group of arenas may allocate.
+///
+/// Cloning a `MemoryBudget` creates another handle to the same budget. Arenas
+/// built with [`ArenaBuilder::budget`](crate::ArenaBuilder::budget) charge it
+/// for every chunk they allocate and refund it when they free the chunk, which
+/// happens when they are dropped or [cleared](crate::Arena::clear).
+///
+/// Once the budget is exhausted, [`try_alloc`](crate::Arena::try_alloc) and
+/// [`try_alloc_extend`](crate::Arena::try_alloc_extend) fail, and other
+/// methods that need a new chunk panic.
+///
+/// ## Example
+///
+/// ```
+/// use typed_arena::{Arena, MemoryBudget};
+///
+/// let budget = MemoryBudget::new(1024);
+/// let a: Arena<u64> = Arena::builder().initial_capacity(64).budget(budget.clone()).build();
+/// let b: Arena<u64> = Arena::builder().initial_capacity(64).budget(budget.clone()).build();
+///
+/// a.alloc(1);
+/// assert_eq!(budget.used(), 512);
+/// b.alloc(2);
+/// assert_eq!(budget.remaining(), 0);
+/// assert_eq!(b.try_alloc_extend(0..64).unwrap_err(), typed_arena::CapacityError);
+///
+/// drop(a);
+/// assert_eq!(budget.used(), 512);
+/// ```
+#[derive(Clone)]
+pub struct MemoryBudget {
+    inner: Arc<Inner>,
+}
+
+struct Inner {
+    limit: usize,
+    used: AtomicUsize,
+}
+
+impl MemoryBudget {
+    /// Create a budget that allows `limit` bytes to be allocated.
+    pub fn new(limit: usize) -> MemoryBudget {
+        MemoryBudget {
+            inner: Arc::new(Inner {
+                limit,
+                used: AtomicUsize::new(0),
+            }),
+        }
+    }
+
+    /// The number of bytes this budget allows.
+    pub fn limit(&self) -> usize {
+        self.inner.limit
+    }
+
+    /// The number of bytes currently charged to this budget.
+    pub fn used(&self) -> usize {
+        self.inner.used.load(Ordering::Relaxed)
+    }
+
+    /// The number of bytes that can still be charged to this budget.
+    pub fn remaining(&self) -> usize {
+        self.limit().saturating_sub(self.used())
+    }
+
+    /// Charge `bytes` to the budget, unless that would exceed its limit.
+    pub(crate) fn try_charge(&self, bytes: usize) -> bool {
+        let limit = self.inner.limit;
+        self.inner
+            .used
+            .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |used| {
+                used.checked_add(bytes).filter(|&used| used <= limit)
+            })
+            .is_ok()
+    }
+
+    /// Give back `bytes` that were charged to the budget.
+    pub(crate) fn refund(&self, bytes: usize) {
+        self.inner.used.fetch_sub(bytes, Ordering::Relaxed);
+    }
+}
+
+impl fmt::Debug for MemoryBudget {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("MemoryBudget")
+            .field("limit", &self.limit())
+            .field("used", &self.used())
+            .finish()
+    }
+}
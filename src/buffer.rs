@@ -0,0 +1,192 @@
+//! An arena that allocates from a buffer provided by the caller.
+
+use core::cell::Cell;
+use core::fmt;
+use core::marker::PhantomData;
+use core::mem::MaybeUninit;
+use core::ptr;
+use core::slice;
+
+use super::drop_elements;
+
+/// The error returned when an arena with a fixed capacity is full.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct CapacityError;
+
+impl fmt::Display for CapacityError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("the arena is full")
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for CapacityError {}
+
+/// An arena of objects of type `T` that allocates from a buffer provided by
+/// the caller.
+///
+/// A `BufferArena` never allocates memory, so it is available without the
+/// `alloc` feature. Once the buffer is full, allocating fails instead of
+/// growing the arena.
+///
+/// ## Example
+///
+/// ```
+/// use std::mem::MaybeUninit;
+/// use typed_arena::BufferArena;
+///
+/// let mut buffer = [MaybeUninit::uninit(); 3];
+/// let arena = BufferArena::from_buffer(&mut buffer);
+///
+/// let one = arena.alloc(1).unwrap();
+/// let rest = arena.alloc_extend(2..4).unwrap();
+/// assert_eq!(*one + rest[0] + rest[1], 6);
+///
+/// assert_eq!(arena.alloc(4), Err(4));
+/// ```
+pub struct BufferArena<'buf, T> {
+    raw: RawBuffer,
+    // Like `Arena`, the arena owns `T`s, but only drops them through `raw`, so
+    // the values in it can reference each other.
+    _marker: PhantomData<(&'buf mut [MaybeUninit<T>], Cell<T>)>,
+}
+
+// The type-erased part of a `BufferArena`, which drops its values.
+struct RawBuffer {
+    start: *mut u8,
+    len: Cell<usize>,
+    capacity: usize,
+    drop_elements: unsafe fn(*mut u8, usize),
+}
+
+unsafe impl<'buf, T: Send> Send for BufferArena<'buf, T> {}
+
+impl<'buf, T> BufferArena<'buf, T> {
+    /// Construct an arena that allocates from `buffer`.
+    ///
+    /// The arena can hold as many values as `buffer` has elements. Values are
+    /// dropped along with the arena, and the buffer is left uninitialized.
+    pub fn from_buffer(buffer: &'buf mut [MaybeUninit<T>]) -> BufferArena<'buf, T> {
+        BufferArena {
+            raw: RawBuffer {
+                start: buffer.as_mut_ptr() as *mut u8,
+                len: Cell::new(0),
+                capacity: buffer.len(),
+                drop_elements: drop_elements::<T>,
+            },
+            _marker: PhantomData,
+        }
+    }
+
+    /// Return the number of values allocated in the arena.
+    #[allow(clippy::len_without_is_empty)]
+    pub fn len(&self) -> usize {
+        self.raw.len.get()
+    }
+
+    /// Return the number of values the arena can hold.
+    pub fn capacity(&self) -> usize {
+        self.raw.capacity
+    }
+
+    /// Allocates a value in the arena, and returns a mutable reference
+    /// to that value.
+    ///
+    /// Returns the value back if the arena is full.
+    ///
+    /// ## Example
+    ///
+    /// ```
+    /// use std::mem::MaybeUninit;
+    /// use typed_arena::BufferArena;
+    ///
+    /// let mut buffer = [MaybeUninit::uninit(); 1];
+    /// let arena = BufferArena::from_buffer(&mut buffer);
+    /// assert_eq!(arena.alloc(42), Ok(&mut 42));
+    /// assert_eq!(arena.alloc(43), Err(43));
+    /// ```
+    #[inline]
+    pub fn alloc(&self, value: T) -> Result<&mut T, T> {
+        let len = self.raw.len.get();
+        if len == self.raw.capacity {
+            return Err(value);
+        }
+        unsafe {
+            let slot = self.slot(len);
+            ptr::write(slot, value);
+            self.raw.len.set(len + 1);
+            Ok(&mut *slot)
+        }
+    }
+
+    /// Uses the contents of an iterator to allocate values in the arena.
+    /// Returns a mutable slice that contains these values.
+    ///
+    /// If the iterator yields more values than the arena has room for, the
+    /// values it yielded are dropped, the arena is left as it was, and an
+    /// error is returned.
+    ///
+    /// ## Example
+    ///
+    /// ```
+    /// use std::mem::MaybeUninit;
+    /// use typed_arena::{BufferArena, CapacityError};
+    ///
+    /// let mut buffer = [MaybeUninit::uninit(); 4];
+    /// let arena = BufferArena::from_buffer(&mut buffer);
+    /// assert_eq!(arena.alloc_extend(0..3), Ok(&mut [0, 1, 2][..]));
+    /// assert_eq!(arena.alloc_extend(0..3), Err(CapacityError));
+    /// assert_eq!(arena.len(), 3);
+    /// ```
+    pub fn alloc_extend<I>(&self, iterable: I) -> Result<&mut [T], CapacityError>
+    where
+        I: IntoIterator<Item = T>,
+    {
+        let iter = iterable.into_iter();
+        let start = self.raw.len.get();
+        if iter.size_hint().0 > self.raw.capacity - start {
+            return Err(CapacityError);
+        }
+
+        let mut len = start;
+        for elem in iter {
+            assert!(
+                self.raw.len.get() == len,
+                "the arena was allocated from while it was consuming an iterator"
+            );
+            if len == self.raw.capacity {
+                drop(elem);
+                unsafe {
+                    self.raw.len.set(start);
+                    (self.raw.drop_elements)(self.slot(start) as *mut u8, len - start);
+                }
+                return Err(CapacityError);
+            }
+            unsafe {
+                ptr::write(self.slot(len), elem);
+            }
+            len += 1;
+            self.raw.len.set(len);
+        }
+        unsafe { Ok(slice::from_raw_parts_mut(self.slot(start), len - start)) }
+    }
+
+    /// Returns an iterator that allows modifying each value.
+    ///
+    /// Items are yielded in the order that they were allocated.
+    pub fn iter_mut(&mut self) -> slice::IterMut<'_, T> {
+        let len = self.raw.len.get();
+        unsafe { slice::from_raw_parts_mut(self.slot(0), len).iter_mut() }
+    }
+
+    /// The `i`th slot of the buffer.
+    unsafe fn slot(&self, i: usize) -> *mut T {
+        (self.raw.start as *mut T).add(i)
+    }
+}
+
+impl Drop for RawBuffer {
+    fn drop(&mut self) {
+        unsafe { (self.drop_elements)(self.start, self.len.get()) }
+    }
+}
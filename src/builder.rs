@@ -0,0 +1,271 @@
+//! Configurable arena construction.
+
+#[cfg(not(feature = "std"))]
+use alloc::borrow::Cow;
+#[cfg(not(feature = "std"))]
+use alloc::boxed::Box;
+#[cfg(not(feature = "std"))]
+use alloc::vec::Vec;
+
+use core::cmp;
+use core::fmt;
+use core::marker::PhantomData;
+use core::mem;
+
+use allocator_api2::alloc::{Allocator, Global};
+#[cfg(feature = "std")]
+use std::borrow::Cow;
+
+#[cfg(feature = "std")]
+use super::ChunkPool;
+use super::{Arena, ArenaObserver, GrowthPolicy, MemoryBudget, INITIAL_SIZE};
+
+/// A builder for arenas with non-default chunk sizing.
+///
+/// Created by [`Arena::builder`]. Arenas built without changing any setting
+/// behave exactly like [`Arena::new`].
+///
+/// ## Example
+///
+/// ```
+/// use typed_arena::{Arena, Fixed};
+///
+/// let arena: Arena<[u8; 64]> = Arena::builder()
+///     .initial_bytes(4096)
+///     .growth(Fixed(64))
+///     .max_chunk_bytes(64 * 1024)
+///     .build();
+///
+/// arena.alloc([0; 64]);
+/// ```
+pub struct ArenaBuilder<T> {
+    initial_capacity: usize,
+    max_chunk_capacity: usize,
+    growth: Option<Box<dyn GrowthPolicy + Send>>,
+    backfill: bool,
+    budget: Option<MemoryBudget>,
+    name: Option<Cow<'static, str>>,
+    observer: Option<Box<dyn ArenaObserver + Send>>,
+    #[cfg(feature = "std")]
+    pool: Option<ChunkPool<T>>,
+    _marker: PhantomData<fn() -> T>,
+}
+
+impl<T> ArenaBuilder<T> {
+    /// Create a builder with the default settings.
+    pub fn new() -> ArenaBuilder<T> {
+        ArenaBuilder {
+            initial_capacity: INITIAL_SIZE / element_size::<T>(),
+            max_chunk_capacity: usize::MAX,
+            growth: None,
+            backfill: false,
+            budget: None,
+            name: None,
+            observer: None,
+            #[cfg(feature = "std")]
+            pool: None,
+            _marker: PhantomData,
+        }
+    }
+
+    /// Set the capacity of the first chunk, in elements.
+    pub fn initial_capacity(mut self, n: usize) -> ArenaBuilder<T> {
+        self.initial_capacity = n;
+        self
+    }
+
+    /// Set the size of the first chunk, in bytes.
+    ///
+    /// The size is rounded down to a whole number of elements.
+    pub fn initial_bytes(mut self, bytes: usize) -> ArenaBuilder<T> {
+        self.initial_capacity = bytes / element_size::<T>();
+        self
+    }
+
+    /// Set the policy that decides the capacity of every chunk after the
+    /// first.
+    ///
+    /// Defaults to [`Doubling`](crate::Doubling).
+    pub fn growth<G>(mut self, policy: G) -> ArenaBuilder<T>
+    where
+        G: GrowthPolicy + Send + 'static,
+    {
+        self.growth = Some(Box::new(policy));
+        self
+    }
+
+    /// Limit the capacity of every chunk, in elements.
+    ///
+    /// The limit is only exceeded when a single call to
+    /// [`alloc_extend`][Arena::alloc_extend] or a related method needs more
+    /// contiguous space than it allows.
+    pub fn max_chunk_capacity(mut self, n: usize) -> ArenaBuilder<T> {
+        self.max_chunk_capacity = n;
+        self
+    }
+
+    /// Limit the size of every chunk, in bytes.
+    ///
+    /// The size is rounded down to a whole number of elements. See
+    /// [`max_chunk_capacity`][ArenaBuilder::max_chunk_capacity] for when the
+    /// limit can be exceeded.
+    pub fn max_chunk_bytes(mut self, bytes: usize) -> ArenaBuilder<T> {
+        self.max_chunk_capacity = if mem::size_of::<T>() == 0 {
+            usize::MAX
+        } else {
+            bytes / mem::size_of::<T>()
+        };
+        self
+    }
+
+    /// Reuse the space that chunks are left with when the arena moves on to a
+    /// new one.
+    ///
+    /// When [`alloc_extend`][Arena::alloc_extend],
+    /// [`reserve_extend`][Arena::reserve_extend] or
+    /// [`alloc_uninitialized`][Arena::alloc_uninitialized] need more
+    /// contiguous space than the current chunk has left, the arena starts a
+    /// new chunk and by default never touches the old chunk's spare capacity
+    /// again. With backfilling enabled, the arena keeps allocating from
+    /// whichever of the two chunks has more room left afterwards, and
+    /// [`alloc`][Arena::alloc] fills the spare capacity of older chunks before
+    /// allocating any further chunks.
+    ///
+    /// The cost is that [`into_vec`][Arena::into_vec] and
+    /// [`iter_mut`][Arena::iter_mut] no longer return values in allocation
+    /// order.
+    ///
+    /// ## Example
+    ///
+    /// ```
+    /// use typed_arena::Arena;
+    ///
+    /// let arena = Arena::builder().initial_capacity(4).backfill(true).build();
+    /// arena.alloc(1);
+    /// arena.alloc_extend(2..10);
+    /// arena.alloc(10);
+    /// arena.alloc(11);
+    ///
+    /// // `10` and `11` went into the space left over in the first chunk.
+    /// assert_eq!(arena.into_vec(), [2, 3, 4, 5, 6, 7, 8, 9, 1, 10, 11]);
+    /// ```
+    pub fn backfill(mut self, enabled: bool) -> ArenaBuilder<T> {
+        self.backfill = enabled;
+        self
+    }
+
+    /// Charge every chunk the arena allocates to `budget`.
+    ///
+    /// See [`MemoryBudget`] for how arenas with a budget behave.
+    pub fn budget(mut self, budget: MemoryBudget) -> ArenaBuilder<T> {
+        self.budget = Some(budget);
+        self
+    }
+
+    /// Give the arena a name, which it reports to its observer and in
+    /// `tracing` events.
+    pub fn name<N>(mut self, name: N) -> ArenaBuilder<T>
+    where
+        N: Into<Cow<'static, str>>,
+    {
+        self.name = Some(name.into());
+        self
+    }
+
+    /// Report the arena's chunk allocations, large `alloc_extend` calls and
+    /// drop to `observer`.
+    ///
+    /// See [`ArenaObserver`] for the events it receives.
+    pub fn observer<O>(mut self, observer: O) -> ArenaBuilder<T>
+    where
+        O: ArenaObserver + Send + 'static,
+    {
+        self.observer = Some(Box::new(observer));
+        self
+    }
+
+    /// Take chunks from `pool` and return them to it, instead of allocating
+    /// and freeing them.
+    ///
+    /// See [`ChunkPool`] for how arenas share a pool. Since pooled chunks come
+    /// from the global allocator, an arena with a pool never allocates from
+    /// the allocator passed to [`build_in`][ArenaBuilder::build_in].
+    ///
+    /// Requires the `std` feature.
+    #[cfg(feature = "std")]
+    pub fn chunk_pool(mut self, pool: ChunkPool<T>) -> ArenaBuilder<T> {
+        self.pool = Some(pool);
+        self
+    }
+
+    /// Build the arena.
+    ///
+    /// Like [`Arena::new`], this doesn't allocate the first chunk until the
+    /// first value is allocated.
+    pub fn build(self) -> Arena<T> {
+        self.build_in(Global)
+    }
+
+    /// Build an arena that allocates its chunks from `alloc`.
+    ///
+    /// ## Example
+    ///
+    /// ```
+    /// # #![cfg_attr(feature = "nightly", feature(allocator_api))]
+    /// extern crate allocator_api2;
+    /// # extern crate typed_arena;
+    ///
+    /// use allocator_api2::alloc::Global;
+    /// use typed_arena::Arena;
+    ///
+    /// let arena = Arena::builder().initial_capacity(16).build_in(Global);
+    /// # arena.alloc(1);
+    /// ```
+    pub fn build_in<A: Allocator>(self, alloc: A) -> Arena<T, A> {
+        let mut arena = Arena::new_in(alloc);
+        arena.chunks.budget = self.budget;
+        arena.chunks.name = self.name;
+        arena.chunks.observer = self.observer;
+        #[cfg(feature = "std")]
+        {
+            arena.chunks.pool = self.pool.map(|pool| pool.raw);
+        }
+        {
+            let chunks = arena.chunks.inner.get_mut();
+            chunks.initial_capacity = cmp::min(self.initial_capacity, self.max_chunk_capacity);
+            chunks.growth = self.growth;
+            chunks.max_chunk_capacity = self.max_chunk_capacity;
+            if self.backfill {
+                chunks.tails = Some(Vec::new());
+            }
+        }
+        arena
+    }
+}
+
+impl<T> Default for ArenaBuilder<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> fmt::Debug for ArenaBuilder<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut debug = f.debug_struct("ArenaBuilder");
+        debug
+            .field("initial_capacity", &self.initial_capacity)
+            .field("max_chunk_capacity", &self.max_chunk_capacity)
+            .field("custom_growth", &self.growth.is_some())
+            .field("backfill", &self.backfill)
+            .field("budget", &self.budget)
+            .field("name", &self.name)
+            .field("observer", &self.observer.is_some());
+        #[cfg(feature = "std")]
+        debug.field("pool", &self.pool);
+        debug.finish()
+    }
+}
+
+fn element_size<T>() -> usize {
+    cmp::max(1, mem::size_of::<T>())
+}
@@ -0,0 +1,65 @@
+//! Attribution of allocations to the code that made them.
+
+use std::cell::RefCell;
+use std::cmp::Reverse;
+use std::collections::HashMap;
+use std::panic::Location;
+
+/// The values one call site allocated in an arena.
+///
+/// Returned by [`Arena::callsite_report`](crate::Arena::callsite_report).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct CallsiteStats {
+    /// Where the arena was called from.
+    pub location: &'static Location<'static>,
+    /// The number of calls from this location.
+    pub calls: usize,
+    /// The number of values these calls allocated.
+    pub values: usize,
+    /// The size of these values.
+    pub bytes: usize,
+}
+
+/// The call sites that allocated in an arena.
+pub(crate) struct Callsites {
+    // Only allocated once something is recorded, so that arenas can still be
+    // created in constants.
+    map: RefCell<Option<HashMap<&'static Location<'static>, CallsiteStats>>>,
+}
+
+impl Callsites {
+    pub(crate) const fn new() -> Callsites {
+        Callsites {
+            map: RefCell::new(None),
+        }
+    }
+
+    /// Attribute `values` values taking up `bytes` bytes to `location`.
+    pub(crate) fn record(&self, location: &'static Location<'static>, values: usize, bytes: usize) {
+        let mut map = self.map.borrow_mut();
+        let stats = map
+            .get_or_insert_with(HashMap::new)
+            .entry(location)
+            .or_insert(CallsiteStats {
+                location,
+                calls: 0,
+                values: 0,
+                bytes: 0,
+            });
+        stats.calls += 1;
+        stats.values += values;
+        stats.bytes += bytes;
+    }
+
+    /// Every call site, the one that allocated the most bytes first.
+    pub(crate) fn report(&self) -> Vec<CallsiteStats> {
+        let map = self.map.borrow();
+        let mut report: Vec<CallsiteStats> = match *map {
+            Some(ref map) => map.values().cloned().collect(),
+            None => Vec::new(),
+        };
+        report.sort_by_key(|stats| (Reverse(stats.bytes), Reverse(stats.calls), stats.location));
+        report
+    }
+}
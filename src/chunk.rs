@@ -0,0 +1,702 @@
+//! The memory behind an arena.
+//!
+//! `ChunkList` never names the element type: it stores the element layout and
+//! a function that drops elements in place instead. This lets it free the
+//! arena's memory in its `Drop` impl without `Arena<T>` having one, so the
+//! compiler keeps allowing the values in an arena to reference each other,
+//! just as it did when the chunks were `Vec<T>`s.
+
+#[cfg(not(feature = "std"))]
+use alloc::borrow::Cow;
+#[cfg(not(feature = "std"))]
+use alloc::boxed::Box;
+#[cfg(not(feature = "std"))]
+use alloc::vec::Vec;
+
+use allocator_api2::alloc::{handle_alloc_error, Allocator, Layout};
+use core::cell::{Cell, RefCell};
+use core::cmp;
+use core::mem;
+use core::ptr::{self, NonNull};
+#[cfg(feature = "std")]
+use std::borrow::Cow;
+
+#[cfg(feature = "debug-callsites")]
+use super::callsites::Callsites;
+#[cfg(feature = "tracing")]
+use super::observer::LARGE_EXTEND_BYTES;
+#[cfg(feature = "std")]
+use super::pool::RawPool;
+use super::{
+    drop_elements, ArenaObserver, ArenaStats, CapacityError, Doubling, GrowthPolicy, MemoryBudget,
+    MIN_CAPACITY,
+};
+
+/// A chunk that is not currently being allocated from.
+pub(crate) struct RawChunk {
+    pub(crate) start: *mut u8,
+    pub(crate) len: usize,
+    pub(crate) capacity: usize,
+}
+
+/// The bookkeeping that only the slow paths need.
+pub(crate) struct Chunks {
+    // Start and capacity of the chunk that `ChunkList::ptr` points into.
+    // Until the first chunk is allocated, the capacity is 0.
+    pub(crate) start: *mut u8,
+    pub(crate) capacity: usize,
+    // The capacity of the first chunk.
+    pub(crate) initial_capacity: usize,
+    // Every earlier chunk, in allocation order.
+    pub(crate) rest: Vec<RawChunk>,
+    pub(crate) growth: Option<Box<dyn GrowthPolicy + Send>>,
+    pub(crate) max_chunk_capacity: usize,
+    // Indices into `rest` of chunks with spare capacity, if backfilling is
+    // enabled.
+    pub(crate) tails: Option<Vec<usize>>,
+    // Whether `lock_capacity` was called, after which no chunks may be
+    // allocated.
+    pub(crate) locked: bool,
+}
+
+pub(crate) struct ChunkList<A: Allocator> {
+    // The next free slot in the current chunk, and the end of that chunk.
+    // Allocating is a compare and bump of `ptr`, which needs neither `inner`
+    // nor a borrow flag. For zero-sized elements, `ptr` counts the
+    // allocated elements in bytes instead.
+    pub(crate) ptr: Cell<*mut u8>,
+    pub(crate) end: Cell<*mut u8>,
+    // Everything else. The slow paths borrow this mutably, which doubles as
+    // the guard against the arena being used reentrantly from the user code
+    // they call.
+    pub(crate) inner: RefCell<Chunks>,
+    // Charged for every chunk while it is allocated.
+    pub(crate) budget: Option<MemoryBudget>,
+    // Where chunks come from and go back to instead of the allocator, if the
+    // arena shares a pool.
+    #[cfg(feature = "std")]
+    pub(crate) pool: Option<RawPool>,
+    // What the arena reports to observers.
+    pub(crate) name: Option<Cow<'static, str>>,
+    pub(crate) observer: Option<Box<dyn ArenaObserver + Send>>,
+    #[cfg(feature = "debug-callsites")]
+    pub(crate) callsites: Callsites,
+    layout: Layout,
+    // `None` if the elements don't need dropping, so that dropping and
+    // clearing the arena never visit them and only free the chunks.
+    drop_elements: Option<unsafe fn(*mut u8, usize)>,
+    // Where the chunks come from.
+    alloc: A,
+}
+
+impl<A: Allocator> ChunkList<A> {
+    /// An empty list for elements of type `T`, which hasn't allocated
+    /// anything yet. Its first chunk will have room for `initial_capacity`
+    /// elements, and all chunks are allocated from `alloc`.
+    pub(crate) const fn new_in<T>(initial_capacity: usize, alloc: A) -> ChunkList<A> {
+        let layout = Layout::new::<T>();
+        let dangling = NonNull::<T>::dangling().as_ptr() as *mut u8;
+        ChunkList {
+            ptr: Cell::new(dangling),
+            end: Cell::new(dangling),
+            inner: RefCell::new(Chunks {
+                start: dangling,
+                capacity: 0,
+                initial_capacity,
+                rest: Vec::new(),
+                growth: None,
+                max_chunk_capacity: usize::MAX,
+                tails: None,
+                locked: false,
+            }),
+            budget: None,
+            #[cfg(feature = "std")]
+            pool: None,
+            name: None,
+            observer: None,
+            #[cfg(feature = "debug-callsites")]
+            callsites: Callsites::new(),
+            layout,
+            drop_elements: if mem::needs_drop::<T>() {
+                Some(drop_elements::<T>)
+            } else {
+                None
+            },
+            alloc,
+        }
+    }
+
+    /// Number of elements that fit in the current chunk after `ptr`.
+    #[inline]
+    pub(crate) fn remaining(&self) -> usize {
+        let bytes = self.end.get() as usize - self.ptr.get() as usize;
+        match self.layout.size() {
+            0 => bytes,
+            size => bytes / size,
+        }
+    }
+
+    /// Number of elements in the current chunk, which starts at `start`.
+    #[inline]
+    pub(crate) fn current_len(&self, start: *mut u8) -> usize {
+        let bytes = self.ptr.get() as usize - start as usize;
+        match self.layout.size() {
+            0 => bytes,
+            size => bytes / size,
+        }
+    }
+
+    /// Start a new chunk with room for at least `additional` elements.
+    ///
+    /// The current chunk is retired, and its spare capacity is not used again
+    /// unless backfilling is enabled.
+    #[inline(never)]
+    #[cold]
+    pub(crate) fn reserve(&self, chunks: &mut Chunks, additional: usize) {
+        if self.try_reserve(chunks, additional).is_err() {
+            self.exhausted(chunks);
+        }
+    }
+
+    /// Like `reserve`, but fails instead of panicking if the arena may not
+    /// allocate another chunk.
+    #[inline(never)]
+    #[cold]
+    pub(crate) fn try_reserve(
+        &self,
+        chunks: &mut Chunks,
+        additional: usize,
+    ) -> Result<(), CapacityError> {
+        let new_capacity = self.next_capacity(chunks, additional)?;
+        let chunk = self.alloc_chunk(chunks, new_capacity)?;
+        self.retire_current(chunks);
+        self.set_current(chunks, chunk);
+        Ok(())
+    }
+
+    /// Panic because the arena can't allocate another chunk.
+    #[cold]
+    pub(crate) fn exhausted(&self, chunks: &Chunks) -> ! {
+        if chunks.locked {
+            panic!("the arena's capacity is locked");
+        }
+        panic!("the arena's memory budget is exhausted");
+    }
+
+    /// The capacity of the chunk after the current one (or of the first
+    /// chunk), which must have room for at least `additional` elements.
+    fn next_capacity(&self, chunks: &Chunks, additional: usize) -> Result<usize, CapacityError> {
+        if chunks.locked {
+            return Err(CapacityError);
+        }
+        let current = chunks.capacity;
+        let new_capacity = if current == 0 && chunks.rest.is_empty() {
+            chunks.initial_capacity
+        } else {
+            match chunks.growth {
+                Some(ref growth) => growth.next_capacity(current, additional),
+                None => Doubling.next_capacity(current, additional),
+            }
+        };
+        let new_capacity = cmp::min(new_capacity, chunks.max_chunk_capacity);
+        Ok(cmp::max(cmp::max(MIN_CAPACITY, additional), new_capacity))
+    }
+
+    /// Move the elements of the current chunk into a new, bigger one with
+    /// room for at least `additional` elements in total, and free the old
+    /// one.
+    ///
+    /// The new chunk is at least twice as big, whatever the growth policy and
+    /// the maximum chunk capacity say, like a `Vec` growing. Otherwise an
+    /// iterator that under-reports its length would make `alloc_extend` copy
+    /// its items over and over.
+    #[inline(never)]
+    #[cold]
+    pub(crate) fn try_grow(
+        &self,
+        chunks: &mut Chunks,
+        additional: usize,
+    ) -> Result<(), CapacityError> {
+        if chunks.locked {
+            return Err(CapacityError);
+        }
+        let len = self.current_len(chunks.start);
+        let doubled = len.checked_mul(2).expect("capacity overflow");
+        let new_capacity = cmp::max(MIN_CAPACITY, cmp::max(doubled, additional));
+        let mut chunk = self.alloc_chunk(chunks, new_capacity)?;
+        unsafe {
+            ptr::copy_nonoverlapping(chunks.start, chunk.start, len * self.layout.size());
+        }
+        chunk.len = len;
+        let old = RawChunk {
+            start: chunks.start,
+            len: 0,
+            capacity: chunks.capacity,
+        };
+        self.set_current(chunks, chunk);
+        self.free_chunk(old);
+        Ok(())
+    }
+
+    /// Allocate an empty chunk with room for `capacity` elements, charging
+    /// the budget for it.
+    ///
+    /// A chunk from the pool may have room for more elements, but never for
+    /// more than the arena's maximum chunk capacity.
+    fn alloc_chunk(&self, chunks: &Chunks, capacity: usize) -> Result<RawChunk, CapacityError> {
+        let max = cmp::max(capacity, chunks.max_chunk_capacity);
+        self.alloc_chunk_up_to(capacity, max)
+    }
+
+    /// Allocate an empty chunk with room for at least `capacity` elements,
+    /// and at most `max` if it comes from the pool, charging the budget for
+    /// it.
+    #[cfg_attr(not(feature = "std"), allow(unused_variables))]
+    fn alloc_chunk_up_to(&self, capacity: usize, max: usize) -> Result<RawChunk, CapacityError> {
+        if self.layout.size() == 0 {
+            return Ok(RawChunk {
+                start: self.layout.align() as *mut u8,
+                len: 0,
+                capacity: usize::MAX,
+            });
+        }
+        #[cfg(feature = "std")]
+        {
+            if let Some(ref pool) = self.pool {
+                let (start, capacity) = pool.take(capacity, max);
+                if !self.charge(capacity) {
+                    unsafe { pool.give(start, capacity) };
+                    return Err(CapacityError);
+                }
+                self.chunk_allocated(capacity);
+                return Ok(RawChunk {
+                    start,
+                    len: 0,
+                    capacity,
+                });
+            }
+        }
+        if !self.charge(capacity) {
+            return Err(CapacityError);
+        }
+        let layout = self.chunk_layout(capacity);
+        let start = match self.alloc.allocate(layout) {
+            Ok(start) => start.as_ptr() as *mut u8,
+            Err(_) => handle_alloc_error(layout),
+        };
+        self.chunk_allocated(capacity);
+        Ok(RawChunk {
+            start,
+            len: 0,
+            capacity,
+        })
+    }
+
+    /// Report a new chunk with room for `capacity` elements.
+    fn chunk_allocated(&self, capacity: usize) {
+        let bytes = self.chunk_layout(capacity).size();
+        #[cfg(feature = "tracing")]
+        tracing::debug!(
+            target: "typed_arena",
+            name = self.name.as_deref(),
+            bytes,
+            "arena allocated a chunk"
+        );
+        if let Some(ref observer) = self.observer {
+            observer.chunk_allocated(self.name.as_deref(), bytes);
+        }
+    }
+
+    /// Report that a bulk allocation allocated `len` elements, if that is a lot.
+    pub(crate) fn extended(&self, len: usize) {
+        let bytes = len * self.layout.size();
+        #[cfg(feature = "tracing")]
+        {
+            if bytes >= LARGE_EXTEND_BYTES {
+                tracing::debug!(
+                    target: "typed_arena",
+                    name = self.name.as_deref(),
+                    bytes,
+                    "large alloc_extend"
+                );
+            }
+        }
+        if let Some(ref observer) = self.observer {
+            if bytes >= observer.large_extend_bytes() {
+                observer.large_extend(self.name.as_deref(), bytes);
+            }
+        }
+    }
+
+    /// Charge the budget, if there is one, for a chunk of `capacity`
+    /// elements.
+    fn charge(&self, capacity: usize) -> bool {
+        match self.budget {
+            Some(ref budget) => budget.try_charge(self.chunk_layout(capacity).size()),
+            None => true,
+        }
+    }
+
+    /// Drop the `len` elements starting at `start`, if they need dropping.
+    unsafe fn drop_elements(&self, start: *mut u8, len: usize) {
+        if let Some(drop_elements) = self.drop_elements {
+            drop_elements(start, len);
+        }
+    }
+
+    /// Drop the elements of `chunk` and free its memory, or return it to the
+    /// pool.
+    pub(crate) fn free_chunk(&self, chunk: RawChunk) {
+        unsafe {
+            self.drop_elements(chunk.start, chunk.len);
+            if self.layout.size() == 0 || chunk.capacity == 0 {
+                return;
+            }
+            let layout = self.chunk_layout(chunk.capacity);
+            if let Some(ref budget) = self.budget {
+                budget.refund(layout.size());
+            }
+            #[cfg(feature = "std")]
+            {
+                if let Some(ref pool) = self.pool {
+                    pool.give(chunk.start, chunk.capacity);
+                    return;
+                }
+            }
+            self.alloc
+                .deallocate(NonNull::new_unchecked(chunk.start), layout);
+        }
+    }
+
+    /// Drop every element and free every chunk but the largest, which becomes
+    /// the current chunk again.
+    pub(crate) fn clear(&self) {
+        let mut chunks = self.inner.borrow_mut();
+        self.retire_current(&mut chunks);
+        let mut all = mem::take(&mut chunks.rest);
+        if let Some(ref mut tails) = chunks.tails {
+            tails.clear();
+        }
+        let largest = (0..all.len()).max_by_key(|&i| all[i].capacity);
+        if let Some(largest) = largest {
+            let mut keep = all.swap_remove(largest);
+            for chunk in all {
+                self.free_chunk(chunk);
+            }
+            let len = mem::replace(&mut keep.len, 0);
+            unsafe { self.drop_elements(keep.start, len) };
+            self.set_current(&mut chunks, keep);
+        }
+    }
+
+    /// Shrink every chunk to fit its elements and free the empty ones.
+    /// Returns the number of bytes freed.
+    ///
+    /// This moves elements, so nothing may borrow them.
+    pub(crate) fn shrink_to_fit(&self) -> usize {
+        let mut chunks = self.inner.borrow_mut();
+        // Shrinking may reallocate, which a locked arena must never do.
+        if self.layout.size() == 0 || chunks.locked {
+            return 0;
+        }
+        let before = self.allocated_bytes(&chunks);
+        self.retire_current(&mut chunks);
+        if let Some(ref mut tails) = chunks.tails {
+            tails.clear();
+        }
+        let all = mem::take(&mut chunks.rest);
+        for chunk in all {
+            if chunk.len == 0 {
+                self.free_chunk(chunk);
+            } else if chunk.len < chunk.capacity {
+                let len = chunk.len;
+                let chunk = self.resize_chunk(chunk, len);
+                chunks.rest.push(chunk);
+            } else {
+                chunks.rest.push(chunk);
+            }
+        }
+        self.make_last_current(&mut chunks);
+        before.saturating_sub(self.allocated_bytes(&chunks))
+    }
+
+    /// Move every element into a single chunk that fits them exactly, and
+    /// free all other chunks. Returns the number of bytes freed.
+    ///
+    /// If the arena's budget doesn't allow for the new chunk, this only
+    /// shrinks the chunks instead. This moves elements, so nothing may borrow
+    /// them.
+    pub(crate) fn compact(&self) -> usize {
+        let mut chunks = self.inner.borrow_mut();
+        if chunks.locked {
+            return 0;
+        }
+        let len = chunks.rest.iter().map(|chunk| chunk.len).sum::<usize>()
+            + self.current_len(chunks.start);
+        if self.layout.size() == 0 || chunks.rest.is_empty() || len == 0 {
+            drop(chunks);
+            return self.shrink_to_fit();
+        }
+        let before = self.allocated_bytes(&chunks);
+        // Coalescing chunks that are already full frees nothing.
+        if len * self.layout.size() >= before {
+            drop(chunks);
+            return self.shrink_to_fit();
+        }
+        // A bigger chunk from the pool would defeat the purpose.
+        let mut target = match self.alloc_chunk_up_to(len, len) {
+            Ok(chunk) => chunk,
+            Err(CapacityError) => {
+                drop(chunks);
+                return self.shrink_to_fit();
+            }
+        };
+        self.retire_current(&mut chunks);
+        if let Some(ref mut tails) = chunks.tails {
+            tails.clear();
+        }
+        let size = self.layout.size();
+        for mut chunk in mem::take(&mut chunks.rest) {
+            unsafe {
+                ptr::copy_nonoverlapping(
+                    chunk.start,
+                    target.start.add(target.len * size),
+                    chunk.len * size,
+                );
+            }
+            target.len += mem::replace(&mut chunk.len, 0);
+            self.free_chunk(chunk);
+        }
+        self.set_current(&mut chunks, target);
+        before.saturating_sub(self.allocated_bytes(&chunks))
+    }
+
+    /// Move the elements of `chunk` into a chunk with room for exactly
+    /// `capacity` elements, which must be at least its length.
+    fn resize_chunk(&self, chunk: RawChunk, capacity: usize) -> RawChunk {
+        let old_layout = self.chunk_layout(chunk.capacity);
+        let new_layout = self.chunk_layout(capacity);
+        if let Some(ref budget) = self.budget {
+            budget.refund(old_layout.size() - new_layout.size());
+        }
+        #[cfg(feature = "std")]
+        {
+            if let Some(ref pool) = self.pool {
+                let (start, capacity) = pool.take(capacity, capacity);
+                unsafe {
+                    ptr::copy_nonoverlapping(chunk.start, start, chunk.len * self.layout.size());
+                    pool.give(chunk.start, chunk.capacity);
+                }
+                return RawChunk {
+                    start,
+                    len: chunk.len,
+                    capacity,
+                };
+            }
+        }
+        let start = unsafe {
+            self.alloc
+                .shrink(NonNull::new_unchecked(chunk.start), old_layout, new_layout)
+        };
+        match start {
+            Ok(start) => RawChunk {
+                start: start.as_ptr() as *mut u8,
+                len: chunk.len,
+                capacity,
+            },
+            Err(_) => handle_alloc_error(new_layout),
+        }
+    }
+
+    /// Make the last chunk in `rest` the current chunk, if there is one.
+    fn make_last_current(&self, chunks: &mut Chunks) {
+        if let Some(chunk) = chunks.rest.pop() {
+            self.set_current(chunks, chunk);
+        }
+    }
+
+    /// Describe how the arena uses its chunks.
+    pub(crate) fn stats(&self) -> ArenaStats {
+        let chunks = self.inner.borrow();
+        let size = self.layout.size();
+        let mut stats = ArenaStats {
+            len: self.current_len(chunks.start),
+            capacity: chunks.capacity,
+            chunks: if chunks.capacity == 0 { 0 } else { 1 },
+            allocated_bytes: self.allocated_bytes(&chunks),
+            wasted_bytes: 0,
+            largest_chunk_capacity: chunks.capacity,
+        };
+        for chunk in chunks.rest.iter() {
+            stats.len += chunk.len;
+            stats.capacity = stats.capacity.saturating_add(chunk.capacity);
+            stats.chunks += 1;
+            stats.wasted_bytes += (chunk.capacity - chunk.len) * size;
+            stats.largest_chunk_capacity = cmp::max(stats.largest_chunk_capacity, chunk.capacity);
+        }
+        stats
+    }
+
+    /// The number of bytes in all chunks.
+    pub(crate) fn allocated_bytes(&self, chunks: &Chunks) -> usize {
+        if self.layout.size() == 0 {
+            return 0;
+        }
+        let capacity = chunks
+            .rest
+            .iter()
+            .map(|chunk| chunk.capacity)
+            .sum::<usize>()
+            + chunks.capacity;
+        capacity * self.layout.size()
+    }
+
+    fn chunk_layout(&self, capacity: usize) -> Layout {
+        array_layout(self.layout, capacity)
+    }
+
+    /// Move the current chunk to the end of `rest`, leaving no current chunk.
+    pub(crate) fn retire_current(&self, chunks: &mut Chunks) {
+        let len = self.current_len(chunks.start);
+        let capacity = mem::replace(&mut chunks.capacity, 0);
+        if capacity != 0 {
+            if let Some(ref mut tails) = chunks.tails {
+                if len < capacity {
+                    tails.push(chunks.rest.len());
+                }
+            }
+            chunks.rest.push(RawChunk {
+                start: chunks.start,
+                len,
+                capacity,
+            });
+        }
+        let dangling = self.layout.align() as *mut u8;
+        chunks.start = dangling;
+        self.ptr.set(dangling);
+        self.end.set(dangling);
+    }
+
+    /// Make `chunk` the current chunk, replacing whatever was current.
+    pub(crate) fn set_current(&self, chunks: &mut Chunks, chunk: RawChunk) {
+        chunks.start = chunk.start;
+        chunks.capacity = chunk.capacity;
+        if self.layout.size() == 0 {
+            self.ptr.set(chunk.start.wrapping_add(chunk.len));
+            self.end.set(usize::MAX as *mut u8);
+        } else {
+            let size = self.layout.size();
+            unsafe {
+                self.ptr.set(chunk.start.add(chunk.len * size));
+                self.end.set(chunk.start.add(chunk.capacity * size));
+            }
+        }
+    }
+
+    /// Remove the last `n` elements from the most recently retired chunk
+    /// without dropping them, and return a pointer to the first one.
+    ///
+    /// The memory stays valid, so the caller can move the elements elsewhere.
+    pub(crate) fn take_back(&self, chunks: &mut Chunks, n: usize) -> *mut u8 {
+        let index = chunks.rest.len() - 1;
+        let chunk = &mut chunks.rest[index];
+        let was_full = chunk.len == chunk.capacity;
+        chunk.len -= n;
+        if let Some(ref mut tails) = chunks.tails {
+            if was_full {
+                tails.push(index);
+            }
+        }
+        if self.layout.size() == 0 {
+            chunk.start
+        } else {
+            unsafe { chunk.start.add(chunk.len * self.layout.size()) }
+        }
+    }
+
+    /// Claim a slot in the spare capacity of a chunk in `rest`, if backfilling
+    /// is enabled and there is one.
+    pub(crate) fn backfill(&self, chunks: &mut Chunks) -> Option<*mut u8> {
+        let tails = chunks.tails.as_mut()?;
+        let index = *tails.last()?;
+        let chunk = &mut chunks.rest[index];
+        let slot = unsafe { chunk.start.add(chunk.len * self.layout.size()) };
+        chunk.len += 1;
+        if chunk.len == chunk.capacity {
+            tails.pop();
+        }
+        Some(slot)
+    }
+
+    /// If backfilling is enabled and the most recently abandoned chunk has more
+    /// spare capacity than the current one, make it current again.
+    ///
+    /// Only the bookkeeping is swapped, so this never moves any items.
+    pub(crate) fn swap_in_tail(&self, chunks: &mut Chunks) {
+        let index = match chunks.tails {
+            Some(ref tails) => match tails.last() {
+                Some(&index) => index,
+                None => return,
+            },
+            None => return,
+        };
+        let tail = &chunks.rest[index];
+        if tail.capacity - tail.len > self.remaining() {
+            let current = RawChunk {
+                start: chunks.start,
+                len: self.current_len(chunks.start),
+                capacity: chunks.capacity,
+            };
+            let tail = mem::replace(&mut chunks.rest[index], current);
+            self.set_current(chunks, tail);
+            let current = &chunks.rest[index];
+            if current.len == current.capacity {
+                if let Some(ref mut tails) = chunks.tails {
+                    tails.pop();
+                }
+            }
+        }
+    }
+}
+
+impl<A: Allocator> Drop for ChunkList<A> {
+    fn drop(&mut self) {
+        #[cfg(feature = "tracing")]
+        {
+            let stats = self.stats();
+            tracing::debug!(
+                target: "typed_arena",
+                name = self.name.as_deref(),
+                len = stats.len,
+                allocated_bytes = stats.allocated_bytes,
+                wasted_bytes = stats.wasted_bytes,
+                "arena dropped"
+            );
+        }
+        if let Some(ref observer) = self.observer {
+            observer.dropped(self.name.as_deref(), &self.stats());
+        }
+        let (start, capacity, rest) = {
+            let chunks = self.inner.get_mut();
+            (chunks.start, chunks.capacity, mem::take(&mut chunks.rest))
+        };
+        let current = RawChunk {
+            start,
+            len: self.current_len(start),
+            capacity,
+        };
+        for chunk in rest {
+            self.free_chunk(chunk);
+        }
+        self.free_chunk(current);
+    }
+}
+
+/// The layout of `capacity` elements with the given layout.
+pub(crate) fn array_layout(element: Layout, capacity: usize) -> Layout {
+    let size = element
+        .size()
+        .checked_mul(capacity)
+        .expect("capacity overflow");
+    Layout::from_size_align(size, element.align()).expect("capacity overflow")
+}
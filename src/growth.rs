@@ -0,0 +1,125 @@
+//! Chunk growth policies.
+//!
+//! When an arena runs out of room in its current chunk it allocates a new one.
+//! A [`GrowthPolicy`] decides how large that new chunk is. Policies are
+//! installed with [`ArenaBuilder::growth`](crate::ArenaBuilder::growth); an
+//! arena without one uses [`Doubling`].
+
+use core::cmp;
+
+/// Decides the capacity of the next chunk an arena allocates.
+///
+/// Capacities are counted in elements, not bytes. The arena never allocates a
+/// chunk smaller than `required`, and clamps the result to the maximum chunk
+/// size configured on the [`ArenaBuilder`](crate::ArenaBuilder), so a policy
+/// doesn't need to enforce either bound itself.
+///
+/// Any `Fn(usize, usize) -> usize` closure is a growth policy, which makes
+/// one-off policies easy to write. For example, rounding every chunk up to a
+/// whole number of 4 KiB pages:
+///
+/// ```
+/// use std::mem;
+/// use typed_arena::{Arena, GrowthPolicy, Doubling};
+///
+/// const PAGE: usize = 4096;
+/// let size = mem::size_of::<u64>();
+///
+/// let arena: Arena<u64> = Arena::builder()
+///     .growth(move |current: usize, required: usize| {
+///         let capacity = Doubling.next_capacity(current, required);
+///         let bytes = (capacity * size + PAGE - 1) / PAGE * PAGE;
+///         bytes / size
+///     })
+///     .build();
+/// # arena.alloc_extend(0..1000);
+/// ```
+pub trait GrowthPolicy {
+    /// Returns the capacity of the next chunk.
+    ///
+    /// `current` is the capacity of the chunk that just ran out of room and
+    /// `required` is the number of contiguous elements the new chunk must be
+    /// able to hold.
+    fn next_capacity(&self, current: usize, required: usize) -> usize;
+}
+
+impl<F> GrowthPolicy for F
+where
+    F: Fn(usize, usize) -> usize,
+{
+    #[inline]
+    fn next_capacity(&self, current: usize, required: usize) -> usize {
+        self(current, required)
+    }
+}
+
+/// Double the capacity of every new chunk.
+///
+/// This is the default policy. It keeps the number of chunks logarithmic in
+/// the number of allocated values.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Doubling;
+
+impl GrowthPolicy for Doubling {
+    fn next_capacity(&self, current: usize, required: usize) -> usize {
+        let double_cap = current.checked_mul(2).expect("capacity overflow");
+        let required_cap = required
+            .checked_next_power_of_two()
+            .expect("capacity overflow");
+        cmp::max(double_cap, required_cap)
+    }
+}
+
+/// Double the capacity of every new chunk until it reaches `max`, then keep
+/// allocating chunks of `max` elements.
+///
+/// Past the cap, the arena's total capacity grows linearly instead of
+/// exponentially.
+///
+/// ## Example
+///
+/// ```
+/// use typed_arena::{Arena, CappedDoubling};
+///
+/// let arena: Arena<u32> = Arena::builder()
+///     .initial_capacity(16)
+///     .growth(CappedDoubling { max: 256 })
+///     .build();
+/// # arena.alloc_extend(0..1000);
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct CappedDoubling {
+    /// The largest capacity this policy asks for.
+    pub max: usize,
+}
+
+impl GrowthPolicy for CappedDoubling {
+    fn next_capacity(&self, current: usize, required: usize) -> usize {
+        cmp::min(Doubling.next_capacity(current, required), self.max)
+    }
+}
+
+/// Allocate every new chunk with the same capacity.
+///
+/// Requests for more contiguous elements than this still get a chunk large
+/// enough to hold them.
+///
+/// ## Example
+///
+/// ```
+/// use typed_arena::{Arena, Fixed};
+///
+/// let arena: Arena<u32> = Arena::builder()
+///     .initial_capacity(64)
+///     .growth(Fixed(64))
+///     .build();
+/// # arena.alloc_extend(0..1000);
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Fixed(pub usize);
+
+impl GrowthPolicy for Fixed {
+    fn next_capacity(&self, _current: usize, _required: usize) -> usize {
+        self.0
+    }
+}
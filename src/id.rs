@@ -0,0 +1,113 @@
+//! Handles to values in an arena.
+
+use core::fmt;
+use core::hash::{Hash, Hasher};
+use core::marker::PhantomData;
+use core::num::NonZeroU32;
+use core::sync::atomic::{AtomicU32, Ordering};
+
+/// A handle to a value allocated with [`Arena::alloc_id`](crate::Arena::alloc_id).
+///
+/// An `Id` is the index of the value in the arena, in allocation order, so
+/// it is small and `Copy`, and doesn't borrow the arena. Use
+/// [`Arena::get`](crate::Arena::get) and
+/// [`Arena::get_mut`](crate::Arena::get_mut) to get at the value.
+///
+/// An `Id` is 64 bits: a 32-bit index and the 32-bit
+/// [generation](crate::Arena::generation) of the arena it came from. Arenas
+/// move on to a new generation after they are
+/// [cleared](crate::Arena::clear), and no two arenas ever share a generation,
+/// so looking an `Id` up in any other arena, or after its arena was cleared,
+/// returns `None` instead of some other value.
+///
+/// ## Example
+///
+/// ```
+/// use std::mem;
+/// use typed_arena::{Arena, Id};
+///
+/// assert_eq!(mem::size_of::<Id<String>>(), 8);
+/// assert_eq!(mem::size_of::<Option<Id<String>>>(), 8);
+///
+/// let mut arena = Arena::new();
+/// let id = arena.alloc_id(String::from("stale"));
+/// arena.clear();
+/// assert_eq!(arena.get(id), None);
+/// assert_ne!(arena.generation(), id.generation());
+/// ```
+pub struct Id<T> {
+    pub(crate) index: u32,
+    pub(crate) generation: NonZeroU32,
+    _marker: PhantomData<fn() -> T>,
+}
+
+impl<T> Id<T> {
+    pub(crate) fn new(index: u32, generation: NonZeroU32) -> Id<T> {
+        Id {
+            index,
+            generation,
+            _marker: PhantomData,
+        }
+    }
+
+    /// The index of the value in its arena.
+    ///
+    /// This is the value's position in [`Arena::into_vec`](crate::Arena::into_vec).
+    pub fn index(self) -> usize {
+        self.index as usize
+    }
+
+    /// The generation of the arena when it allocated the value.
+    pub fn generation(self) -> u32 {
+        self.generation.get()
+    }
+}
+
+impl<T> Clone for Id<T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T> Copy for Id<T> {}
+
+impl<T> PartialEq for Id<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.index == other.index && self.generation == other.generation
+    }
+}
+
+impl<T> Eq for Id<T> {}
+
+impl<T> Hash for Id<T> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.index.hash(state);
+        self.generation.hash(state);
+    }
+}
+
+impl<T> fmt::Debug for Id<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Id")
+            .field("index", &self.index)
+            .field("generation", &self.generation)
+            .finish()
+    }
+}
+
+/// Return a generation that no arena has used before.
+///
+/// Arenas move on to a new generation when they hand out an id after their
+/// values stopped matching the ids they handed out before, so that `get` can't
+/// return a value that was allocated with `alloc` and may still be borrowed.
+///
+/// Panics once all `u32::MAX` generations have been used.
+pub(crate) fn next_generation() -> NonZeroU32 {
+    static NEXT: AtomicU32 = AtomicU32::new(1);
+    let tag = NEXT
+        .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |tag| {
+            tag.checked_add(1)
+        })
+        .expect("ran out of arena generations");
+    NonZeroU32::new(tag).unwrap()
+}
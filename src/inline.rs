@@ -0,0 +1,308 @@
+//! An arena that keeps its first few values inline.
+
+#[cfg(not(feature = "std"))]
+use alloc::vec::Vec;
+
+use core::cell::{Cell, UnsafeCell};
+use core::mem::{self, MaybeUninit};
+use core::ptr;
+use core::slice;
+
+use super::{Arena, IterMut};
+
+/// An arena of objects of type `T` that stores its first `N` values inline.
+///
+/// Values beyond the first `N` spill into an ordinary [`Arena`], so an
+/// `InlineArena` never allocates on the heap unless it ends up holding more
+/// than `N` values. This makes it a good fit for the many arenas that only
+/// ever hold a handful of values.
+///
+/// Since the inline values are part of the `InlineArena` itself, they move
+/// along with it. That's fine as far as the borrow checker is concerned,
+/// because the arena can't be moved while anything it allocated is still
+/// borrowed.
+///
+/// Unlike [`Arena`], an `InlineArena` has to drop its inline values itself,
+/// so the values in it can't hold references to each other.
+///
+/// ## Example
+///
+/// ```
+/// use typed_arena::InlineArena;
+///
+/// let arena: InlineArena<u32, 4> = InlineArena::new();
+/// arena.alloc(1);
+/// arena.alloc_extend(2..4);
+/// assert!(!arena.spilled());
+///
+/// arena.alloc_extend(4..10);
+/// assert!(arena.spilled());
+/// assert_eq!(arena.into_vec(), [1, 2, 3, 4, 5, 6, 7, 8, 9]);
+/// ```
+pub struct InlineArena<T, const N: usize> {
+    inline: UnsafeCell<[MaybeUninit<T>; N]>,
+    // Number of initialized values at the start of `inline`.
+    len: Cell<usize>,
+    // Once values have gone to `spill`, later ones go there too, so that they
+    // stay in allocation order.
+    spilled: Cell<bool>,
+    pub(crate) spill: Arena<T>,
+}
+
+impl<T, const N: usize> InlineArena<T, N> {
+    /// Construct a new arena.
+    ///
+    /// This never allocates.
+    ///
+    /// ## Example
+    ///
+    /// ```
+    /// use typed_arena::InlineArena;
+    ///
+    /// let arena = InlineArena::<_, 16>::new();
+    /// # arena.alloc(1);
+    /// ```
+    pub const fn new() -> InlineArena<T, N> {
+        InlineArena {
+            // An array of `MaybeUninit`s doesn't need to be initialized.
+            inline: UnsafeCell::new(unsafe { MaybeUninit::uninit().assume_init() }),
+            len: Cell::new(0),
+            spilled: Cell::new(false),
+            spill: Arena::new(),
+        }
+    }
+
+    /// Return the number of values allocated in the arena.
+    ///
+    /// ## Example
+    ///
+    /// ```
+    /// use typed_arena::InlineArena;
+    ///
+    /// let arena = InlineArena::<_, 2>::new();
+    /// arena.alloc_extend(0..3);
+    /// assert_eq!(arena.len(), 3);
+    /// ```
+    #[allow(clippy::len_without_is_empty)]
+    pub fn len(&self) -> usize {
+        self.len.get() + self.spill.len()
+    }
+
+    /// Returns `true` once the arena has run out of inline space and started
+    /// allocating values on the heap.
+    pub fn spilled(&self) -> bool {
+        self.spilled.get()
+    }
+
+    /// Allocates a value in the arena, and returns a mutable reference
+    /// to that value.
+    ///
+    /// ## Example
+    ///
+    /// ```
+    /// use typed_arena::InlineArena;
+    ///
+    /// let arena = InlineArena::<_, 8>::new();
+    /// let x = arena.alloc(42);
+    /// assert_eq!(*x, 42);
+    /// ```
+    #[inline]
+    #[cfg_attr(feature = "debug-callsites", track_caller)]
+    pub fn alloc(&self, value: T) -> &mut T {
+        let len = self.len.get();
+        if len == N || self.spilled.get() {
+            self.spilled.set(true);
+            return self.spill.alloc(value);
+        }
+        unsafe {
+            let slot = self.slot(len);
+            ptr::write(slot, value);
+            self.len.set(len + 1);
+            #[cfg(feature = "debug-callsites")]
+            self.spill.record_callsite(1);
+            &mut *slot
+        }
+    }
+
+    /// Uses the contents of an iterator to allocate values in the arena.
+    /// Returns a mutable slice that contains these values.
+    ///
+    /// The values are stored inline if they all fit in the remaining inline
+    /// space, and in the spilled arena otherwise.
+    ///
+    /// ## Example
+    ///
+    /// ```
+    /// use typed_arena::InlineArena;
+    ///
+    /// let arena = InlineArena::<_, 8>::new();
+    /// let abc = arena.alloc_extend("abcdefg".chars().take(3));
+    /// assert_eq!(abc, ['a', 'b', 'c']);
+    /// ```
+    #[cfg_attr(feature = "debug-callsites", track_caller)]
+    pub fn alloc_extend<I>(&self, iterable: I) -> &mut [T]
+    where
+        I: IntoIterator<Item = T>,
+    {
+        let mut iter = iterable.into_iter();
+        let start = self.len.get();
+        if self.spilled.get() || iter.size_hint().0 > N - start {
+            self.spilled.set(true);
+            return self.spill.alloc_extend(iter);
+        }
+
+        let mut len = start;
+        let overflow = loop {
+            let elem = match iter.next() {
+                Some(elem) => elem,
+                None => {
+                    #[cfg(feature = "debug-callsites")]
+                    self.spill.record_callsite(len - start);
+                    return unsafe { slice::from_raw_parts_mut(self.slot(start), len - start) };
+                }
+            };
+            assert!(
+                self.len.get() == len,
+                "the arena was allocated from while it was consuming an iterator"
+            );
+            if len == N {
+                break elem;
+            }
+            unsafe {
+                ptr::write(self.slot(len), elem);
+            }
+            len += 1;
+            self.len.set(len);
+        };
+
+        // The iterator doesn't fit inline after all, so move what it yielded
+        // so far over to the spilled arena along with the rest of it.
+        let mut moved = Vec::with_capacity(len - start + 1 + iter.size_hint().0);
+        unsafe {
+            moved.extend((start..len).map(|i| ptr::read(self.slot(i))));
+        }
+        self.len.set(start);
+        self.spilled.set(true);
+        moved.push(overflow);
+        moved.extend(iter);
+        self.spill.alloc_extend(moved)
+    }
+
+    /// Returns a report of the values allocated in the arena, by the
+    /// location of the call that allocated them, with the call site that
+    /// allocated the most bytes first.
+    ///
+    /// This covers values stored inline as well as spilled ones. See
+    /// [`Arena::callsite_report`].
+    ///
+    /// ## Example
+    ///
+    /// ```
+    /// use typed_arena::InlineArena;
+    ///
+    /// let arena = InlineArena::<u32, 4>::new();
+    /// arena.alloc(0);
+    /// arena.alloc_extend(1..100);
+    ///
+    /// let report = arena.callsite_report();
+    /// assert_eq!((report[0].calls, report[0].bytes), (1, 396));
+    /// assert_eq!((report[1].calls, report[1].bytes), (1, 4));
+    /// ```
+    #[cfg(feature = "debug-callsites")]
+    pub fn callsite_report(&self) -> Vec<crate::CallsiteStats> {
+        self.spill.callsite_report()
+    }
+
+    /// Returns an iterator that allows modifying each value.
+    ///
+    /// Items are yielded in the order that they were allocated.
+    ///
+    /// ## Example
+    ///
+    /// ```
+    /// use typed_arena::InlineArena;
+    ///
+    /// let mut arena = InlineArena::<_, 2>::new();
+    /// arena.alloc_extend(0..4);
+    /// for value in arena.iter_mut() {
+    ///     *value *= 10;
+    /// }
+    /// assert_eq!(arena.into_vec(), [0, 10, 20, 30]);
+    /// ```
+    pub fn iter_mut(&mut self) -> InlineIterMut<'_, T> {
+        let len = self.len.get();
+        let inline = unsafe { slice::from_raw_parts_mut(self.slot(0), len) };
+        InlineIterMut {
+            inline: inline.iter_mut(),
+            spill: self.spill.iter_mut(),
+        }
+    }
+
+    /// Convert this `InlineArena` into a `Vec<T>`.
+    ///
+    /// Items in the resulting `Vec<T>` appear in the order that they were
+    /// allocated in.
+    ///
+    /// ## Example
+    ///
+    /// ```
+    /// use typed_arena::InlineArena;
+    ///
+    /// let arena = InlineArena::<_, 2>::new();
+    /// arena.alloc("a");
+    /// arena.alloc_extend(vec!["b", "c"]);
+    /// assert_eq!(arena.into_vec(), ["a", "b", "c"]);
+    /// ```
+    pub fn into_vec(mut self) -> Vec<T> {
+        let len = self.len.replace(0);
+        let spill = mem::take(&mut self.spill);
+        let mut vec = Vec::with_capacity(len + spill.len());
+        unsafe {
+            vec.extend((0..len).map(|i| ptr::read(self.slot(i))));
+        }
+        vec.extend(spill.into_vec());
+        vec
+    }
+
+    /// The `i`th inline slot.
+    unsafe fn slot(&self, i: usize) -> *mut T {
+        (self.inline.get() as *mut T).add(i)
+    }
+}
+
+impl<T, const N: usize> Default for InlineArena<T, N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T, const N: usize> Drop for InlineArena<T, N> {
+    fn drop(&mut self) {
+        let len = self.len.get();
+        unsafe {
+            ptr::drop_in_place(ptr::slice_from_raw_parts_mut(self.slot(0), len));
+        }
+    }
+}
+
+/// Mutable arena iterator.
+///
+/// This struct is created by the [`iter_mut`](InlineArena::iter_mut) method on
+/// [InlineArena].
+pub struct InlineIterMut<'a, T: 'a> {
+    inline: slice::IterMut<'a, T>,
+    spill: IterMut<'a, T>,
+}
+
+impl<'a, T> Iterator for InlineIterMut<'a, T> {
+    type Item = &'a mut T;
+
+    fn next(&mut self) -> Option<&'a mut T> {
+        self.inline.next().or_else(|| self.spill.next())
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let len = self.inline.len() + self.spill.size_hint().0;
+        (len, Some(len))
+    }
+}
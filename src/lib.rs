@@ -55,35 +55,152 @@
 // 3) use an iterator. This may add far too much unsafe code.
 
 #![deny(missing_docs)]
+#![allow(clippy::mut_from_ref)]
 #![cfg_attr(not(any(feature = "std", test)), no_std)]
+#![cfg_attr(feature = "nightly", feature(allocator_api))]
 
-#[cfg(not(feature = "std"))]
+#[cfg(all(feature = "alloc", not(feature = "std")))]
 extern crate alloc;
+#[cfg(feature = "alloc")]
+extern crate allocator_api2;
+#[cfg(feature = "tracing")]
+extern crate tracing;
 
 #[cfg(any(feature = "std", test))]
 extern crate core;
 
-#[cfg(not(feature = "std"))]
+#[cfg(all(feature = "alloc", not(feature = "std")))]
+use alloc::boxed::Box;
+#[cfg(all(feature = "alloc", not(feature = "std")))]
 use alloc::vec::Vec;
 
-use core::cell::RefCell;
+#[cfg(feature = "alloc")]
+use core::cell::Cell;
+#[cfg(feature = "alloc")]
 use core::cmp;
-use core::iter;
-use core::mem;
+#[cfg(feature = "alloc")]
+use core::convert::TryFrom;
+#[cfg(feature = "alloc")]
+use core::fmt;
+#[cfg(feature = "alloc")]
+use core::marker::PhantomData;
+#[cfg(feature = "alloc")]
+use core::mem::{self, MaybeUninit};
+#[cfg(feature = "alloc")]
+use core::num::NonZeroU32;
 use core::ptr;
+#[cfg(feature = "alloc")]
+use core::ptr::NonNull;
+#[cfg(feature = "alloc")]
 use core::slice;
+#[cfg(feature = "alloc")]
 use core::str;
+#[cfg(feature = "debug-callsites")]
+use std::panic::Location;
 
-use mem::MaybeUninit;
+#[cfg(feature = "alloc")]
+use allocator_api2::alloc::{Allocator, Global};
+#[cfg(feature = "alloc")]
+use chunk::{ChunkList, RawChunk};
 
-#[cfg(test)]
+#[cfg(feature = "std")]
+mod arena_pool;
+mod arena_ref;
+#[cfg(feature = "alloc")]
+mod brand;
+#[cfg(feature = "alloc")]
+mod budget;
+mod buffer;
+#[cfg(feature = "alloc")]
+mod builder;
+#[cfg(feature = "debug-callsites")]
+mod callsites;
+#[cfg(feature = "alloc")]
+mod chunk;
+#[cfg(feature = "alloc")]
+mod growth;
+#[cfg(feature = "alloc")]
+mod id;
+#[cfg(feature = "alloc")]
+mod inline;
+#[cfg(feature = "alloc")]
+mod observer;
+#[cfg(feature = "alloc")]
+mod owned;
+#[cfg(feature = "alloc")]
+mod pinned;
+#[cfg(feature = "std")]
+mod pool;
+#[cfg(feature = "alloc")]
+mod rc;
+#[cfg(feature = "alloc")]
+mod stats;
+#[cfg(feature = "std")]
+mod sync;
+
+#[cfg(all(test, feature = "alloc"))]
 mod test;
 
+#[cfg(feature = "std")]
+pub use arena_pool::{ArenaPool, PooledArena};
+pub use arena_ref::ArenaRef;
+#[cfg(feature = "alloc")]
+pub use brand::{BrandedArena, BrandedId};
+#[cfg(feature = "alloc")]
+pub use budget::MemoryBudget;
+pub use buffer::{BufferArena, CapacityError};
+#[cfg(feature = "alloc")]
+pub use builder::ArenaBuilder;
+#[cfg(feature = "debug-callsites")]
+pub use callsites::CallsiteStats;
+#[cfg(feature = "alloc")]
+pub use growth::{CappedDoubling, Doubling, Fixed, GrowthPolicy};
+#[cfg(feature = "alloc")]
+pub use id::Id;
+#[cfg(feature = "alloc")]
+pub use inline::{InlineArena, InlineIterMut};
+#[cfg(feature = "alloc")]
+pub use observer::ArenaObserver;
+#[cfg(feature = "alloc")]
+pub use owned::{ArenaRoot, OwnedArena};
+#[cfg(feature = "alloc")]
+pub use pinned::PinnedArena;
+#[cfg(feature = "std")]
+pub use pool::ChunkPool;
+#[cfg(feature = "std")]
+pub use rc::{ArcArena, ArenaArc};
+#[cfg(feature = "alloc")]
+pub use rc::{ArenaRc, RcArena};
+#[cfg(feature = "alloc")]
+pub use stats::ArenaStats;
+#[cfg(feature = "std")]
+pub use sync::SyncArena;
+
 // Initial size in bytes.
+#[cfg(feature = "alloc")]
 const INITIAL_SIZE: usize = 1024;
 // Minimum capacity. Must be larger than 0.
+#[cfg(feature = "alloc")]
 const MIN_CAPACITY: usize = 1;
 
+/// Drop `len` values of type `T` starting at `start`.
+///
+/// Arenas store this as a function pointer, so that the part of them that
+/// drops their values doesn't need to name `T`.
+unsafe fn drop_elements<T>(start: *mut u8, len: usize) {
+    ptr::drop_in_place(ptr::slice_from_raw_parts_mut(start as *mut T, len));
+}
+
+/// The `index`th element of type `T` in the chunk that starts at `start`.
+#[cfg(feature = "alloc")]
+unsafe fn element<T>(start: *mut u8, index: usize) -> *mut T {
+    if mem::size_of::<T>() == 0 {
+        NonNull::dangling().as_ptr()
+    } else {
+        (start as *mut T).add(index)
+    }
+}
+
 /// An arena of objects of type `T`.
 ///
 /// ## Example
@@ -100,18 +217,44 @@ const MIN_CAPACITY: usize = 1;
 /// let vegeta = monsters.alloc(Monster { level: 9001 });
 /// assert!(vegeta.level > 9000);
 /// ```
-pub struct Arena<T> {
-    chunks: RefCell<ChunkList<T>>,
+///
+/// ## Values that don't need dropping
+///
+/// If `T` has no destructor, as told by [`mem::needs_drop`], dropping or
+/// [clearing](Arena::clear) the arena never visits its values, and only frees
+/// its chunks.
+///
+/// ## Allocators
+///
+/// The arena allocates its chunks from `A`, which can be any allocator that
+/// implements the [`Allocator`] trait of the [`allocator-api2`] crate. With the
+/// `nightly` feature, that trait is the standard library's unstable
+/// `Allocator` instead.
+///
+/// [`allocator-api2`]: https://docs.rs/allocator-api2
+#[cfg(feature = "alloc")]
+pub struct Arena<T, A: Allocator = Global> {
+    chunks: ChunkList<A>,
+    // The arena owns `T`s, which `ChunkList` doesn't say, and must be
+    // invariant over `T`, like the `RefCell<Vec<T>>` it used to be.
+    _marker: PhantomData<Cell<T>>,
+    // The generation of the ids handed out by `alloc_id`, or 0 if it hasn't
+    // handed out any yet.
+    generation: Cell<u32>,
 }
 
-struct ChunkList<T> {
-    current: Vec<T>,
-    rest: Vec<Vec<T>>,
-}
+#[cfg(feature = "alloc")]
+unsafe impl<T: Send, A: Allocator + Send> Send for Arena<T, A> {}
 
+#[cfg(feature = "alloc")]
 impl<T> Arena<T> {
     /// Construct a new arena.
     ///
+    /// The arena doesn't allocate any memory until the first value is
+    /// allocated in it, so empty arenas are cheap. Since this is a `const fn`,
+    /// arenas can also be created in constants and `static`s; see
+    /// [`SyncArena`] for an arena that can be shared between threads.
+    ///
     /// ## Example
     ///
     /// ```
@@ -120,9 +263,8 @@ impl<T> Arena<T> {
     /// let arena = Arena::new();
     /// # arena.alloc(1);
     /// ```
-    pub fn new() -> Arena<T> {
-        let size = cmp::max(1, mem::size_of::<T>());
-        Arena::with_capacity(INITIAL_SIZE / size)
+    pub const fn new() -> Arena<T> {
+        Arena::new_in(Global)
     }
 
     /// Construct a new arena with capacity for `n` values pre-allocated.
@@ -136,12 +278,93 @@ impl<T> Arena<T> {
     /// # arena.alloc(1);
     /// ```
     pub fn with_capacity(n: usize) -> Arena<T> {
+        Arena::with_capacity_in(n, Global)
+    }
+
+    /// Start building an arena with custom chunk sizing.
+    ///
+    /// See [`ArenaBuilder`] for the available settings.
+    ///
+    /// ## Example
+    ///
+    /// ```
+    /// use typed_arena::{Arena, CappedDoubling};
+    ///
+    /// let arena: Arena<u64> = Arena::builder()
+    ///     .initial_capacity(32)
+    ///     .growth(CappedDoubling { max: 4096 })
+    ///     .build();
+    /// # arena.alloc(1);
+    /// ```
+    pub fn builder() -> ArenaBuilder<T> {
+        ArenaBuilder::new()
+    }
+
+    /// Call `f` with a new [`BrandedArena`], whose ids can't be used with any
+    /// other arena.
+    ///
+    /// See [`BrandedArena`] for an example.
+    pub fn with_brand<F, R>(f: F) -> R
+    where
+        F: for<'brand> FnOnce(BrandedArena<'brand, T>) -> R,
+    {
+        f(BrandedArena::new(Arena::new()))
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl<T, A: Allocator> Arena<T, A> {
+    /// Construct a new arena that allocates its chunks from `alloc`.
+    ///
+    /// Like [`Arena::new`], this doesn't allocate anything yet.
+    ///
+    /// ## Example
+    ///
+    /// ```
+    /// # #![cfg_attr(feature = "nightly", feature(allocator_api))]
+    /// extern crate allocator_api2;
+    /// # extern crate typed_arena;
+    ///
+    /// use allocator_api2::alloc::Global;
+    /// use typed_arena::Arena;
+    ///
+    /// let arena = Arena::new_in(Global);
+    /// # arena.alloc(1);
+    /// ```
+    pub const fn new_in(alloc: A) -> Arena<T, A> {
+        let size = mem::size_of::<T>();
+        let size = if size == 0 { 1 } else { size };
+        Arena {
+            chunks: ChunkList::new_in::<T>(INITIAL_SIZE / size, alloc),
+            _marker: PhantomData,
+            generation: Cell::new(0),
+        }
+    }
+
+    /// Construct a new arena that allocates its chunks from `alloc`, with
+    /// capacity for `n` values pre-allocated.
+    ///
+    /// ## Example
+    ///
+    /// ```
+    /// # #![cfg_attr(feature = "nightly", feature(allocator_api))]
+    /// extern crate allocator_api2;
+    /// # extern crate typed_arena;
+    ///
+    /// use allocator_api2::alloc::Global;
+    /// use typed_arena::Arena;
+    ///
+    /// let arena = Arena::with_capacity_in(1337, Global);
+    /// # arena.alloc(1);
+    /// ```
+    pub fn with_capacity_in(n: usize, alloc: A) -> Arena<T, A> {
         let n = cmp::max(MIN_CAPACITY, n);
+        let chunks = ChunkList::new_in::<T>(n, alloc);
+        chunks.reserve(&mut chunks.inner.borrow_mut(), n);
         Arena {
-            chunks: RefCell::new(ChunkList {
-                current: Vec::with_capacity(n),
-                rest: Vec::new(),
-            }),
+            chunks,
+            _marker: PhantomData,
+            generation: Cell::new(0),
         }
     }
 
@@ -160,15 +383,38 @@ impl<T> Arena<T> {
     ///
     ///  assert_eq!(arena.len(), 2);
     /// ```
+    #[allow(clippy::len_without_is_empty)]
     pub fn len(&self) -> usize {
-        let chunks = self.chunks.borrow();
+        let chunks = self.chunks.inner.borrow();
 
         let mut res = 0;
-        for vec in chunks.rest.iter() {
-            res += vec.len()
+        for chunk in chunks.rest.iter() {
+            res += chunk.len
         }
 
-        res + chunks.current.len()
+        res + self.chunks.current_len(chunks.start)
+    }
+
+    /// Return statistics about the arena's memory.
+    ///
+    /// See [`ArenaStats`] for what they include. The memory of a
+    /// [leaked](Arena::leak) arena is never freed, so it is counted for as
+    /// long as the program runs.
+    ///
+    /// ## Example
+    ///
+    /// ```
+    /// use typed_arena::Arena;
+    ///
+    /// let arena: Arena<u64> = Arena::with_capacity(16);
+    /// arena.alloc(1);
+    ///
+    /// let stats = arena.stats();
+    /// assert_eq!(stats.capacity, 16);
+    /// assert_eq!(stats.allocated_bytes, 16 * 8);
+    /// ```
+    pub fn stats(&self) -> ArenaStats {
+        self.chunks.stats()
     }
 
     /// Allocates a value in the arena, and returns a mutable reference
@@ -184,28 +430,399 @@ impl<T> Arena<T> {
     /// assert_eq!(*x, 42);
     /// ```
     #[inline]
+    #[cfg_attr(feature = "debug-callsites", track_caller)]
     pub fn alloc(&self, value: T) -> &mut T {
-        self.alloc_fast_path(value)
-            .unwrap_or_else(|value| self.alloc_slow_path(value))
+        let slot = if self.chunks.ptr.get() == self.chunks.end.get() {
+            self.alloc_slow_path()
+        } else {
+            unsafe { self.bump(1) }
+        };
+        #[cfg(feature = "debug-callsites")]
+        self.record_callsite(1);
+        unsafe {
+            ptr::write(slot, value);
+            &mut *slot
+        }
+    }
+
+    /// Find a slot for `alloc` when the current chunk is full.
+    ///
+    /// This doesn't take the value, so that `alloc` can write it straight into
+    /// the slot instead of keeping a copy around for this path.
+    #[inline(never)]
+    #[cold]
+    fn alloc_slow_path(&self) -> *mut T {
+        let mut chunks = self.chunks.inner.borrow_mut();
+        match self.chunks.backfill(&mut chunks) {
+            Some(slot) => slot as *mut T,
+            None => {
+                self.chunks.reserve(&mut chunks, 1);
+                unsafe { self.bump(1) }
+            }
+        }
     }
 
+    /// Allocates a value in the arena without growing it, and returns a
+    /// mutable reference to that value.
+    ///
+    /// This is the same as [`alloc`][Arena::alloc] unless the arena's
+    /// capacity was locked with [`lock_capacity`][Arena::lock_capacity] or its
+    /// [`MemoryBudget`] is exhausted, in which case the value is handed back
+    /// when the arena would need another chunk.
+    ///
+    /// ## Example
+    ///
+    /// ```
+    /// use typed_arena::Arena;
+    ///
+    /// let arena = Arena::with_capacity(1);
+    /// arena.lock_capacity();
+    /// assert_eq!(arena.try_alloc(1), Ok(&mut 1));
+    /// assert_eq!(arena.try_alloc(2), Err(2));
+    /// ```
     #[inline]
-    fn alloc_fast_path(&self, value: T) -> Result<&mut T, T> {
-        let mut chunks = self.chunks.borrow_mut();
-        let len = chunks.current.len();
-        if len < chunks.current.capacity() {
-            chunks.current.push(value);
-            // Avoid going through `Vec::deref_mut`, which overlaps
-            // other references we have already handed out!
-            debug_assert!(len < chunks.current.len()); // bounds check
-            Ok(unsafe { &mut *chunks.current.as_mut_ptr().add(len) })
+    #[cfg_attr(feature = "debug-callsites", track_caller)]
+    pub fn try_alloc(&self, value: T) -> Result<&mut T, T> {
+        let slot = if self.chunks.ptr.get() == self.chunks.end.get() {
+            let mut chunks = self.chunks.inner.borrow_mut();
+            match self.chunks.backfill(&mut chunks) {
+                Some(slot) => slot as *mut T,
+                None => match self.chunks.try_reserve(&mut chunks, 1) {
+                    Ok(()) => unsafe { self.bump(1) },
+                    Err(_) => return Err(value),
+                },
+            }
         } else {
-            Err(value)
+            unsafe { self.bump(1) }
+        };
+        #[cfg(feature = "debug-callsites")]
+        self.record_callsite(1);
+        unsafe {
+            ptr::write(slot, value);
+            Ok(&mut *slot)
         }
     }
 
-    fn alloc_slow_path(&self, value: T) -> &mut T {
-        &mut self.alloc_extend(iter::once(value))[0]
+    /// Uses the contents of an iterator to allocate values in the arena
+    /// without growing it. Returns a mutable slice that contains these values.
+    ///
+    /// This is the same as [`alloc_extend`][Arena::alloc_extend] unless the
+    /// arena's capacity was locked with [`lock_capacity`][Arena::lock_capacity]
+    /// or its [`MemoryBudget`] is exhausted. Then, if the values need another
+    /// chunk, the values the iterator yielded are dropped, the arena is left
+    /// as it was, and an error is returned.
+    ///
+    /// ## Example
+    ///
+    /// ```
+    /// use typed_arena::{Arena, CapacityError};
+    ///
+    /// let arena = Arena::with_capacity(4);
+    /// arena.lock_capacity();
+    /// assert_eq!(arena.try_alloc_extend(0..3), Ok(&mut [0, 1, 2][..]));
+    /// assert_eq!(arena.try_alloc_extend(0..3), Err(CapacityError));
+    /// assert_eq!(arena.len(), 3);
+    /// ```
+    #[cfg_attr(feature = "debug-callsites", track_caller)]
+    pub fn try_alloc_extend<I>(&self, iterable: I) -> Result<&mut [T], CapacityError>
+    where
+        I: IntoIterator<Item = T>,
+    {
+        self.alloc_extend_inner(iterable.into_iter())
+    }
+
+    /// Return how many values each call site allocated in the arena, the
+    /// call site that allocated the most bytes first.
+    ///
+    /// This counts every call to the arena's allocating methods since it was
+    /// created, including for values that were cleared since. Requires the
+    /// `debug-callsites` feature, which makes those methods
+    /// `#[track_caller]` and slows them down considerably.
+    ///
+    /// ## Example
+    ///
+    /// ```
+    /// use typed_arena::Arena;
+    ///
+    /// let arena: Arena<u32> = Arena::new();
+    /// arena.alloc_extend(0..100);
+    /// for i in 0..10 {
+    ///     arena.alloc(i);
+    /// }
+    ///
+    /// let report = arena.callsite_report();
+    /// assert_eq!((report[0].calls, report[0].bytes), (1, 400));
+    /// assert_eq!((report[1].calls, report[1].bytes), (10, 40));
+    /// ```
+    #[cfg(feature = "debug-callsites")]
+    pub fn callsite_report(&self) -> Vec<CallsiteStats> {
+        self.chunks.callsites.report()
+    }
+
+    /// Attribute `n` values to the caller of the public method.
+    #[cfg(feature = "debug-callsites")]
+    #[track_caller]
+    fn record_callsite(&self, n: usize) {
+        self.chunks
+            .callsites
+            .record(Location::caller(), n, n * mem::size_of::<T>());
+    }
+
+    /// Allocates a value in the arena, and returns an [`Id`] for it.
+    ///
+    /// Unlike a reference, an `Id` doesn't borrow the arena, so it can be
+    /// stored anywhere. Use [`get`][Arena::get] and [`get_mut`][Arena::get_mut]
+    /// to get at the value.
+    ///
+    /// ## Panics
+    ///
+    /// Panics if the arena was built with
+    /// [`backfill`][ArenaBuilder::backfill] enabled, since ids rely on values
+    /// staying in allocation order, if the arena holds more than `u32::MAX`
+    /// values, or if the process has run out of
+    /// [generations][Arena::generation].
+    ///
+    /// ## Example
+    ///
+    /// ```
+    /// use std::collections::HashMap;
+    /// use typed_arena::Arena;
+    ///
+    /// let arena = Arena::new();
+    /// let mut by_name = HashMap::new();
+    /// by_name.insert("one", arena.alloc_id(1));
+    /// by_name.insert("two", arena.alloc_id(2));
+    ///
+    /// assert_eq!(arena.get(by_name["two"]), Some(&2));
+    /// ```
+    #[cfg_attr(feature = "debug-callsites", track_caller)]
+    pub fn alloc_id(&self, value: T) -> Id<T> {
+        assert!(
+            self.chunks.inner.borrow().tails.is_none(),
+            "ids can't be used with an arena that backfills"
+        );
+        let index = self.len();
+        let index = u32::try_from(index).expect("too many values in the arena for an id");
+        let generation = match NonZeroU32::new(self.generation.get()) {
+            Some(generation) => generation,
+            None => {
+                let generation = id::next_generation();
+                self.generation.set(generation.get());
+                generation
+            }
+        };
+        self.alloc(value);
+        Id::new(index, generation)
+    }
+
+    /// Returns a reference to the value `id` refers to.
+    ///
+    /// Returns `None` if `id` came from another arena, or from an older
+    /// [generation][Arena::generation] of this one.
+    ///
+    /// ## Example
+    ///
+    /// ```
+    /// use typed_arena::Arena;
+    ///
+    /// let arena = Arena::new();
+    /// let id = arena.alloc_id("hello");
+    /// assert_eq!(arena.get(id), Some(&"hello"));
+    ///
+    /// let other = Arena::new();
+    /// other.alloc_id("world");
+    /// assert_eq!(other.get(id), None);
+    /// ```
+    pub fn get(&self, id: Id<T>) -> Option<&T> {
+        // Values allocated with `alloc_id` are only reachable through ids, so
+        // handing out shared references to them is fine.
+        self.slot(id).map(|slot| unsafe { &*slot })
+    }
+
+    /// Returns a mutable reference to the value `id` refers to.
+    ///
+    /// Returns `None` if `id` came from another arena, or from an older
+    /// [generation][Arena::generation] of this one.
+    pub fn get_mut(&mut self, id: Id<T>) -> Option<&mut T> {
+        self.slot(id).map(|slot| unsafe { &mut *slot })
+    }
+
+    /// Returns the arena's generation, which the [`Id`]s it hands out are
+    /// tagged with.
+    ///
+    /// This is 0 until the arena hands out its first `Id`. Every call to
+    /// [`clear`][Arena::clear] makes all older ids invalid and resets it to 0,
+    /// and the next `Id` moves the arena on to a new generation, which no
+    /// other arena has had.
+    ///
+    /// Generations are drawn from a single process-wide 32-bit counter, so
+    /// after about four billion of them, `alloc_id` panics rather than reuse
+    /// one.
+    pub fn generation(&self) -> u32 {
+        self.generation.get()
+    }
+
+    /// The slot of the value `id` refers to, if it belongs to this arena.
+    fn slot(&self, id: Id<T>) -> Option<*mut T> {
+        if id.generation.get() != self.generation.get() || id.index() >= self.len() {
+            return None;
+        }
+        Some(unsafe { self.slot_unchecked(id.index()) })
+    }
+
+    /// The slot of the `index`th value, which must be less than `len()`.
+    unsafe fn slot_unchecked(&self, mut index: usize) -> *mut T {
+        let chunks = self.chunks.inner.borrow();
+        for chunk in chunks.rest.iter() {
+            if index < chunk.len {
+                return element(chunk.start, index);
+            }
+            index -= chunk.len;
+        }
+        element(chunks.start, index)
+    }
+
+    /// Drop every value in the arena, keeping its largest chunk for reuse.
+    ///
+    /// All other chunks are freed, or returned to the arena's `ChunkPool` if
+    /// it has one, and refunded to its [`MemoryBudget`] if it has one.
+    /// [`Id`]s the arena handed out before no longer refer to anything, and
+    /// the next one it hands out has a new [generation][Arena::generation].
+    ///
+    /// ## Example
+    ///
+    /// ```
+    /// use typed_arena::Arena;
+    ///
+    /// let mut arena = Arena::with_capacity(4);
+    /// arena.alloc_extend(0..10);
+    /// arena.clear();
+    /// assert_eq!(arena.len(), 0);
+    ///
+    /// // There's room for ten values without allocating again.
+    /// arena.lock_capacity();
+    /// assert!(arena.try_alloc_extend(0..10).is_ok());
+    /// ```
+    pub fn clear(&mut self) {
+        self.chunks.clear();
+        // The next `alloc_id` draws a new generation, so clearing an arena
+        // that hands out no more ids doesn't use one up.
+        self.generation.set(0);
+    }
+
+    /// Free the arena's spare capacity. Returns the number of bytes freed.
+    ///
+    /// Every chunk is shrunk to fit the values in it, which may move them, and
+    /// chunks without values are freed. The values keep their order. The
+    /// next value allocated after this starts a new chunk.
+    ///
+    /// With a [`ChunkPool`], the freed memory goes back to
+    /// the pool, and values are always moved into a smaller chunk from it.
+    ///
+    /// Shrinking a chunk may reallocate it, so on an arena whose capacity is
+    /// [locked](Arena::lock_capacity) this does nothing and returns 0.
+    ///
+    /// ## Example
+    ///
+    /// ```
+    /// use typed_arena::Arena;
+    ///
+    /// let mut arena: Arena<u64> = Arena::with_capacity(1024);
+    /// arena.alloc_extend(0..100);
+    /// assert_eq!(arena.shrink_to_fit(), 924 * 8);
+    /// assert_eq!(arena.shrink_to_fit(), 0);
+    /// ```
+    pub fn shrink_to_fit(&mut self) -> usize {
+        self.chunks.shrink_to_fit()
+    }
+
+    /// Move all values into a single chunk that fits them exactly, and free
+    /// all other chunks. Returns the number of bytes freed.
+    ///
+    /// This is like [`shrink_to_fit`][Arena::shrink_to_fit], but it also
+    /// coalesces the many small chunks an arena is left with after growing.
+    /// The values keep their order. Since all values are copied into a new
+    /// chunk, the arena briefly needs memory for them twice. If its
+    /// [`MemoryBudget`] doesn't allow for that, or if the chunks are already
+    /// full so that coalescing them would free nothing, this only shrinks the
+    /// chunks. On an arena whose capacity is [locked](Arena::lock_capacity),
+    /// it does nothing and returns 0.
+    ///
+    /// ## Example
+    ///
+    /// ```
+    /// use typed_arena::Arena;
+    ///
+    /// let mut arena = Arena::with_capacity(4);
+    /// for i in 0..100 {
+    ///     arena.alloc(i);
+    /// }
+    /// arena.compact();
+    /// assert_eq!(arena.into_vec(), (0..100).collect::<Vec<_>>());
+    /// ```
+    pub fn compact(&mut self) -> usize {
+        self.chunks.compact()
+    }
+
+    /// Stop the arena from ever allocating more memory.
+    ///
+    /// Afterwards, [`try_alloc`][Arena::try_alloc] and
+    /// [`try_alloc_extend`][Arena::try_alloc_extend] fail once the memory the
+    /// arena already has is used up, and every other method that would need
+    /// to allocate a new chunk panics instead. This is meant for code that
+    /// must not touch the heap, such as realtime threads: allocate what the
+    /// arena needs up front with [`with_capacity`][Arena::with_capacity] or
+    /// [`reserve_extend`][Arena::reserve_extend], then lock it.
+    ///
+    /// Note that an arena created with [`new`][Arena::new] has no memory until
+    /// its first value is allocated.
+    ///
+    /// [`shrink_to_fit`][Arena::shrink_to_fit] and
+    /// [`compact`][Arena::compact] do nothing on a locked arena.
+    ///
+    /// ## Example
+    ///
+    /// ```
+    /// use typed_arena::Arena;
+    ///
+    /// let arena = Arena::with_capacity(256);
+    /// // Warm up...
+    /// arena.alloc(0.0);
+    /// arena.lock_capacity();
+    ///
+    /// // ...and from now on, never allocate.
+    /// while let Ok(_) = arena.try_alloc(1.0) {}
+    /// assert_eq!(arena.len(), 256);
+    /// ```
+    pub fn lock_capacity(&self) {
+        self.chunks.inner.borrow_mut().locked = true;
+    }
+
+    /// Claim the next `n` slots of the current chunk, which the caller has
+    /// made sure has room for them, and return a pointer to the first one.
+    ///
+    /// The arena considers the slots initialized from now on.
+    #[inline]
+    unsafe fn bump(&self, n: usize) -> *mut T {
+        let ptr = self.chunks.ptr.get();
+        if mem::size_of::<T>() == 0 {
+            self.chunks.ptr.set(ptr.wrapping_add(n));
+            NonNull::dangling().as_ptr()
+        } else {
+            let slot = ptr as *mut T;
+            self.chunks.ptr.set(slot.add(n) as *mut u8);
+            slot
+        }
+    }
+
+    /// Panic if `alloc_extend` and friends find that the user code they called
+    /// allocated from the arena, which would split up their items.
+    #[inline]
+    fn check_not_reentered(&self, expected: *mut u8) {
+        assert!(
+            self.chunks.ptr.get() == expected,
+            "the arena was allocated from while it was consuming an iterator"
+        );
     }
 
     /// Uses the contents of an iterator to allocate values in the arena.
@@ -220,60 +837,165 @@ impl<T> Arena<T> {
     /// let abc = arena.alloc_extend("abcdefg".chars().take(3));
     /// assert_eq!(abc, ['a', 'b', 'c']);
     /// ```
+    #[cfg_attr(feature = "debug-callsites", track_caller)]
     pub fn alloc_extend<I>(&self, iterable: I) -> &mut [T]
     where
         I: IntoIterator<Item = T>,
     {
-        let mut iter = iterable.into_iter();
+        match self.alloc_extend_inner(iterable.into_iter()) {
+            Ok(slice) => slice,
+            Err(_) => self.chunks.exhausted(&self.chunks.inner.borrow()),
+        }
+    }
 
-        let mut chunks = self.chunks.borrow_mut();
+    /// `alloc_extend`, but if the arena may not grow when it needs to, it
+    /// drops the items it pushed and fails.
+    #[cfg_attr(feature = "debug-callsites", track_caller)]
+    fn alloc_extend_inner<I>(&self, iter: I) -> Result<&mut [T], CapacityError>
+    where
+        I: Iterator<Item = T>,
+    {
+        let mut chunks = self.chunks.inner.borrow_mut();
 
         let iter_min_len = iter.size_hint().0;
-        let mut next_item_index;
-        debug_assert!(
-            chunks.current.capacity() >= chunks.current.len(),
-            "capacity is always greater than or equal to len, so we don't need to worry about underflow"
-        );
-        if iter_min_len > chunks.current.capacity() - chunks.current.len() {
-            chunks.reserve(iter_min_len);
-            chunks.current.extend(iter);
-            next_item_index = 0;
-        } else {
-            next_item_index = chunks.current.len();
-            let mut i = 0;
-            while let Some(elem) = iter.next() {
-                if chunks.current.len() == chunks.current.capacity() {
-                    // The iterator was larger than we could fit into the current chunk.
-                    let chunks = &mut *chunks;
-                    // Create a new chunk into which we can freely push the entire iterator into
-                    chunks.reserve(i + 1);
-                    let previous_chunk = chunks.rest.last_mut().unwrap();
-                    let previous_chunk_len = previous_chunk.len();
-                    // Move any elements we put into the previous chunk into this new chunk
-                    chunks
-                        .current
-                        .extend(previous_chunk.drain(previous_chunk_len - i..));
-                    chunks.current.push(elem);
-                    // And the remaining elements in the iterator
-                    chunks.current.extend(iter);
-                    next_item_index = 0;
-                    break;
+        if iter_min_len > self.chunks.remaining() {
+            self.chunks.try_reserve(&mut chunks, iter_min_len)?;
+        }
+
+        let mut start = unsafe { self.bump(0) };
+        let mut len = 0;
+        let mut expected = self.chunks.ptr.get();
+        for elem in iter {
+            self.check_not_reentered(expected);
+            if self.chunks.remaining() == 0 {
+                // The iterator was larger than we could fit into the current chunk.
+                let result = if len > 0 && start as *mut u8 == chunks.start {
+                    // The chunk holds nothing but our items, so replace it with a
+                    // bigger one, like a `Vec` would.
+                    self.chunks
+                        .try_grow(&mut chunks, len + 1)
+                        .map(|()| start = chunks.start as *mut T)
                 } else {
-                    chunks.current.push(elem);
+                    // Create a new chunk into which we can freely push the entire iterator into
+                    self.chunks.try_reserve(&mut chunks, len + 1).map(|()| {
+                        if len > 0 {
+                            // Move any elements we put into the previous chunk into this new chunk
+                            let previous = self.chunks.take_back(&mut chunks, len) as *mut T;
+                            unsafe {
+                                start = self.bump(len);
+                                ptr::copy_nonoverlapping(previous, start, len);
+                            }
+                        } else {
+                            start = unsafe { self.bump(0) };
+                        }
+                    })
+                };
+                if let Err(err) = result {
+                    drop(elem);
+                    unsafe { self.roll_back(start, len) };
+                    return Err(err);
                 }
-                i += 1;
             }
+            unsafe {
+                let slot = self.bump(1);
+                ptr::write(slot, elem);
+            }
+            len += 1;
+            expected = self.chunks.ptr.get();
         }
+        self.chunks.swap_in_tail(&mut chunks);
+        self.chunks.extended(len);
+        #[cfg(feature = "debug-callsites")]
+        self.record_callsite(len);
 
-        // Extend the lifetime from that of `chunks_borrow` to that of `self`.
+        // Extend the lifetime from that of `chunks` to that of `self`.
         // This is OK because we’re careful to never move items
-        // by never pushing to inner `Vec`s beyond their initial capacity.
+        // once `alloc_extend` has returned them.
         // The returned reference is unique (`&mut`):
         // the `Arena` never gives away references to existing items.
-        unsafe {
-            let new_len = chunks.current.len() - next_item_index;
-            slice::from_raw_parts_mut(chunks.current.as_mut_ptr().add(next_item_index), new_len)
+        unsafe { Ok(slice::from_raw_parts_mut(start, len)) }
+    }
+
+    /// Drop the last `len` items of the current chunk, which start at
+    /// `start`, and give their slots back.
+    unsafe fn roll_back(&self, start: *mut T, len: usize) {
+        let first = if mem::size_of::<T>() == 0 {
+            self.chunks.ptr.get().wrapping_sub(len)
+        } else {
+            start as *mut u8
+        };
+        self.chunks.ptr.set(first);
+        ptr::drop_in_place(ptr::slice_from_raw_parts_mut(start, len));
+    }
+
+    /// Uses the contents of an iterator to allocate values in the arena,
+    /// without ever moving a value once it has been pushed.
+    ///
+    /// Returns the values as a list of contiguous segments, in iteration
+    /// order. The first segment fills whatever room is left in the current
+    /// chunk and each further segment starts a new chunk, so no segment is
+    /// empty.
+    ///
+    /// [`alloc_extend`][Arena::alloc_extend] returns a single slice instead,
+    /// but when the iterator yields more items than its `size_hint` promised,
+    /// it has to move the items it already pushed into a bigger chunk.
+    ///
+    /// ## Example
+    ///
+    /// ```
+    /// use typed_arena::Arena;
+    ///
+    /// let arena = Arena::with_capacity(4);
+    /// arena.alloc(0);
+    ///
+    /// let segments = arena.alloc_extend_segmented((1..10).filter(|n| n % 2 == 1));
+    /// assert_eq!(segments, [&[1, 3, 5][..], &[7, 9][..]]);
+    /// ```
+    #[cfg_attr(feature = "debug-callsites", track_caller)]
+    pub fn alloc_extend_segmented<I>(&self, iterable: I) -> Vec<&mut [T]>
+    where
+        I: IntoIterator<Item = T>,
+    {
+        let mut iter = iterable.into_iter();
+        let mut segments = Vec::new();
+
+        let mut chunks = self.chunks.inner.borrow_mut();
+        let mut start = unsafe { self.bump(0) };
+        let mut len = 0;
+        let mut expected = self.chunks.ptr.get();
+        while let Some(elem) = iter.next() {
+            self.check_not_reentered(expected);
+            if self.chunks.remaining() == 0 {
+                if len > 0 {
+                    segments.push((start, len));
+                }
+                let remaining = iter.size_hint().0.saturating_add(1);
+                self.chunks.reserve(&mut chunks, remaining);
+                start = unsafe { self.bump(0) };
+                len = 0;
+            }
+            unsafe {
+                let slot = self.bump(1);
+                ptr::write(slot, elem);
+            }
+            len += 1;
+            expected = self.chunks.ptr.get();
         }
+        if len > 0 {
+            segments.push((start, len));
+        }
+        self.chunks.swap_in_tail(&mut chunks);
+        let total = segments.iter().map(|&(_, len)| len).sum();
+        self.chunks.extended(total);
+        #[cfg(feature = "debug-callsites")]
+        self.record_callsite(total);
+
+        // Extend the lifetimes to that of `self`, as `alloc_extend` does.
+        // Segments live in distinct chunks, so they never overlap.
+        segments
+            .into_iter()
+            .map(|(ptr, len)| unsafe { slice::from_raw_parts_mut(ptr, len) })
+            .collect()
     }
 
     /// Allocates space for a given number of values, but doesn't initialize it.
@@ -337,11 +1059,11 @@ impl<T> Arena<T> {
     /// unsafe {
     ///     // Perform initialization before we claim the memory.
     ///     let uninitialized = arena.uninitialized_array();
-    ///     assert!((*uninitialized).len() >= COUNT); // Ensured by the reserve_extend
-    ///     for elem in &mut (*uninitialized)[..COUNT] {
+    ///     assert!((&*uninitialized).len() >= COUNT); // Ensured by the reserve_extend
+    ///     for elem in &mut (&mut *uninitialized)[..COUNT] {
     ///         ptr::write(elem.as_mut_ptr(), "Hello".to_owned());
     ///     }
-    ///     let addr = (*uninitialized).as_ptr() as usize;
+    ///     let addr = (&*uninitialized).as_ptr() as usize;
     ///
     ///     // The alloc_uninitialized returns the same memory, but "confirms" its allocation.
     ///     slice = transmute_uninit(arena.alloc_uninitialized(COUNT));
@@ -349,42 +1071,93 @@ impl<T> Arena<T> {
     ///     assert_eq!(slice, &["Hello".to_owned(), "Hello".to_owned()]);
     /// }
     /// ```
+    #[cfg_attr(feature = "debug-callsites", track_caller)]
     pub unsafe fn alloc_uninitialized(&self, num: usize) -> &mut [MaybeUninit<T>] {
-        let mut chunks = self.chunks.borrow_mut();
+        let mut chunks = self.chunks.inner.borrow_mut();
 
-        debug_assert!(
-            chunks.current.capacity() >= chunks.current.len(),
-            "capacity is always greater than or equal to len, so we don't need to worry about underflow"
-        );
-        if num > chunks.current.capacity() - chunks.current.len() {
-            chunks.reserve(num);
+        if num > self.chunks.remaining() {
+            self.chunks.reserve(&mut chunks, num);
         }
 
         // At this point, the current chunk must have free capacity.
-        let next_item_index = chunks.current.len();
-        chunks.current.set_len(next_item_index + num);
-
         // Go through pointers, to make sure we never create a reference to uninitialized T.
-        let start = chunks.current.as_mut_ptr().offset(next_item_index as isize);
+        let start = self.bump(num);
         let start_uninit = start as *mut MaybeUninit<T>;
+        self.chunks.swap_in_tail(&mut chunks);
+        self.chunks.extended(num);
+        #[cfg(feature = "debug-callsites")]
+        self.record_callsite(num);
         slice::from_raw_parts_mut(start_uninit, num)
     }
 
+    /// Allocate a slot and have `init` initialize it in place.
+    ///
+    /// The arena can't allocate anything else while `init` runs, so that
+    /// nothing can be put in the slot before it is claimed: allocating from
+    /// `init` panics. If `init` panics, the slot is given back.
+    ///
+    /// `init` must initialize the slot before it returns.
+    #[cfg_attr(feature = "debug-callsites", track_caller)]
+    pub(crate) unsafe fn alloc_in_place<F>(&self, init: F) -> &mut T
+    where
+        F: FnOnce(*mut T),
+    {
+        struct Reopen<'a> {
+            ptr: &'a Cell<*mut u8>,
+            end: &'a Cell<*mut u8>,
+            before: *mut u8,
+            saved_end: *mut u8,
+            initialized: bool,
+        }
+
+        impl<'a> Drop for Reopen<'a> {
+            fn drop(&mut self) {
+                self.end.set(self.saved_end);
+                if !self.initialized {
+                    self.ptr.set(self.before);
+                }
+            }
+        }
+
+        // Held while `init` runs, so that the slow path of `alloc` and the
+        // other allocating methods panic instead of reentering.
+        let mut chunks = self.chunks.inner.borrow_mut();
+        if self.chunks.remaining() == 0 {
+            self.chunks.reserve(&mut chunks, 1);
+        }
+        let before = self.chunks.ptr.get();
+        let slot = self.bump(1);
+        let mut reopen = Reopen {
+            ptr: &self.chunks.ptr,
+            end: &self.chunks.end,
+            before,
+            saved_end: self.chunks.end.get(),
+            initialized: false,
+        };
+        // Make the fast path of `alloc` find the chunk full.
+        self.chunks.end.set(self.chunks.ptr.get());
+        init(slot);
+        reopen.initialized = true;
+        drop(reopen);
+
+        self.chunks.swap_in_tail(&mut chunks);
+        #[cfg(feature = "debug-callsites")]
+        self.record_callsite(1);
+        &mut *slot
+    }
+
     /// Makes sure there's enough continuous space for at least `num` elements.
     ///
     /// This may save some work if called before [`alloc_extend`][Arena::alloc_extend]. It also
     /// allows somewhat safer use pattern of [`alloc_uninitialized`][Arena::alloc_uninitialized].
     /// On the other hand this might waste up to `n - 1` elements of space. In case new allocation
-    /// is needed, the unused ones in current chunk are never used.
+    /// is needed, the unused ones in current chunk are never used, unless the arena was built with
+    /// [`backfill`][ArenaBuilder::backfill] enabled.
     pub fn reserve_extend(&self, num: usize) {
-        let mut chunks = self.chunks.borrow_mut();
+        let mut chunks = self.chunks.inner.borrow_mut();
 
-        debug_assert!(
-            chunks.current.capacity() >= chunks.current.len(),
-            "capacity is always greater than or equal to len, so we don't need to worry about underflow"
-        );
-        if num > chunks.current.capacity() - chunks.current.len() {
-            chunks.reserve(num);
+        if num > self.chunks.remaining() {
+            self.chunks.reserve(&mut chunks, num);
         }
     }
 
@@ -398,22 +1171,19 @@ impl<T> Arena<T> {
     /// It returns a raw pointer to avoid creating multiple mutable references to the same place.
     /// It is up to the caller not to dereference it after any of the `alloc_` methods are called.
     pub fn uninitialized_array(&self) -> *mut [MaybeUninit<T>] {
-        let mut chunks = self.chunks.borrow_mut();
-        let len = chunks.current.capacity() - chunks.current.len();
-        let next_item_index = chunks.current.len();
+        let len = self.chunks.remaining();
 
-        unsafe {
-            // Go through pointers, to make sure we never create a reference to uninitialized T.
-            let start = chunks.current.as_mut_ptr().offset(next_item_index as isize);
-            let start_uninit = start as *mut MaybeUninit<T>;
-            ptr::slice_from_raw_parts_mut(start_uninit, len)
-        }
+        // Go through pointers, to make sure we never create a reference to uninitialized T.
+        let start = unsafe { self.bump(0) };
+        let start_uninit = start as *mut MaybeUninit<T>;
+        ptr::slice_from_raw_parts_mut(start_uninit, len)
     }
 
     /// Convert this `Arena` into a `Vec<T>`.
     ///
     /// Items in the resulting `Vec<T>` appear in the order that they were
-    /// allocated in.
+    /// allocated in, unless the arena was built with
+    /// [`backfill`][ArenaBuilder::backfill] enabled.
     ///
     /// ## Example
     ///
@@ -431,23 +1201,71 @@ impl<T> Arena<T> {
     /// assert_eq!(easy_as_123, vec!["a", "b", "c"]);
     /// ```
     pub fn into_vec(self) -> Vec<T> {
-        let mut chunks = self.chunks.into_inner();
+        let mut chunks = self.chunks.inner.borrow_mut();
+        self.chunks.retire_current(&mut chunks);
         // keep order of allocation in the resulting Vec
-        let n = chunks
-            .rest
-            .iter()
-            .fold(chunks.current.len(), |a, v| a + v.len());
-        let mut result = Vec::with_capacity(n);
-        for mut vec in chunks.rest {
-            result.append(&mut vec);
+        let n = chunks.rest.iter().map(|chunk| chunk.len).sum();
+        let mut result: Vec<T> = Vec::with_capacity(n);
+        for chunk in chunks.rest.iter_mut() {
+            // Move the items out, and leave the chunk to free only its memory.
+            unsafe {
+                let dst = result.as_mut_ptr().add(result.len());
+                ptr::copy_nonoverlapping(chunk.start as *const T, dst, chunk.len);
+                result.set_len(result.len() + chunk.len);
+            }
+            chunk.len = 0;
         }
-        result.append(&mut chunks.current);
         result
     }
 
+    /// Leak the arena, so that it and its values live until the program
+    /// exits.
+    ///
+    /// Values allocated in a leaked arena are `&'static mut T`, which suits
+    /// data that is built once and used for the rest of the program, like a
+    /// compiler's symbol table.
+    ///
+    /// The arena is never dropped, so the destructors of its values never
+    /// run. Its chunks are never freed, refunded to its
+    /// [budget](ArenaBuilder::budget) or given back to its
+    /// [chunk pool](ArenaBuilder::chunk_pool), and its
+    /// [observer](ArenaBuilder::observer) never sees it dropped.
+    /// [`stats`](Arena::stats) keeps counting all of its memory.
+    ///
+    /// A leaked `Arena` can only be used on the thread that leaked it. Use a
+    /// `static` [`SyncArena`] for values that are shared between threads.
+    ///
+    /// ## Example
+    ///
+    /// ```
+    /// use typed_arena::Arena;
+    ///
+    /// fn intern(symbols: &'static Arena<u8>, name: &str) -> &'static str {
+    ///     symbols.alloc_str(name)
+    /// }
+    ///
+    /// # // Miri reports the arena as a leak.
+    /// # #[cfg(not(miri))]
+    /// # fn main() {
+    /// let symbols = Arena::new().leak();
+    /// let main = intern(symbols, "main");
+    /// assert_eq!(main, "main");
+    /// # }
+    /// # #[cfg(miri)]
+    /// # fn main() {}
+    /// ```
+    pub fn leak(self) -> &'static Arena<T, A>
+    where
+        T: 'static,
+        A: 'static,
+    {
+        Box::leak(Box::new(self))
+    }
+
     /// Returns an iterator that allows modifying each value.
     ///
-    /// Items are yielded in the order that they were allocated.
+    /// Items are yielded in the order that they were allocated, unless the arena
+    /// was built with [`backfill`][ArenaBuilder::backfill] enabled.
     ///
     /// ## Example
     ///
@@ -492,30 +1310,27 @@ impl<T> Arena<T> {
     /// *x = 2;
     /// ```
     #[inline]
-    pub fn iter_mut(&mut self) -> IterMut<T> {
-        let chunks = self.chunks.get_mut();
-        let position = if !chunks.rest.is_empty() {
-            let index = 0;
-            let inner_iter = chunks.rest[index].iter_mut();
-            // Extend the lifetime of the individual elements to that of the arena.
-            // This is OK because we borrow the arena mutably to prevent new allocations
-            // and we take care here to never move items inside the arena while the
-            // iterator is alive.
-            let inner_iter = unsafe { mem::transmute(inner_iter) };
-            IterMutState::ChunkListRest { index, inner_iter }
-        } else {
-            // Extend the lifetime of the individual elements to that of the arena.
-            let iter = unsafe { mem::transmute(chunks.current.iter_mut()) };
-            IterMutState::ChunkListCurrent { iter }
-        };
+    pub fn iter_mut(&mut self) -> IterMut<'_, T> {
+        let start = self.chunks.inner.get_mut().start;
+        let current_len = self.chunks.current_len(start);
+        let chunks = self.chunks.inner.get_mut();
+        let remaining = chunks.rest.iter().fold(current_len, |a, c| a + c.len);
+        // Extend the lifetime of the individual elements to that of the arena.
+        // This is OK because we borrow the arena mutably to prevent new allocations
+        // and we take care here to never move items inside the arena while the
+        // iterator is alive.
+        let current = unsafe { slice::from_raw_parts_mut(start as *mut T, current_len) };
         IterMut {
-            chunks,
-            state: position,
+            rest: chunks.rest.iter(),
+            current: Some(current),
+            inner: [].iter_mut(),
+            remaining,
         }
     }
 }
 
-impl Arena<u8> {
+#[cfg(feature = "alloc")]
+impl<A: Allocator> Arena<u8, A> {
     /// Allocates a string slice and returns a mutable reference to it.
     ///
     /// This is on `Arena<u8>`, because string slices use byte slices (`[u8]`) as their backing
@@ -531,6 +1346,7 @@ impl Arena<u8> {
     /// assert_eq!("Hello world", hello);
     /// ```
     #[inline]
+    #[cfg_attr(feature = "debug-callsites", track_caller)]
     pub fn alloc_str(&self, s: &str) -> &mut str {
         let buffer = self.alloc_extend(s.bytes());
         // Can't fail the utf8 validation, it already came in as utf8
@@ -538,98 +1354,60 @@ impl Arena<u8> {
     }
 }
 
-impl<T> Default for Arena<T> {
+#[cfg(feature = "alloc")]
+impl<T, A: Allocator + Default> Default for Arena<T, A> {
     fn default() -> Self {
-        Self::new()
+        Self::new_in(A::default())
     }
 }
 
-impl<T> ChunkList<T> {
-    #[inline(never)]
-    #[cold]
-    fn reserve(&mut self, additional: usize) {
-        let double_cap = self
-            .current
-            .capacity()
-            .checked_mul(2)
-            .expect("capacity overflow");
-        let required_cap = additional
-            .checked_next_power_of_two()
-            .expect("capacity overflow");
-        let new_capacity = cmp::max(double_cap, required_cap);
-        let chunk = mem::replace(&mut self.current, Vec::with_capacity(new_capacity));
-        self.rest.push(chunk);
+/// Prints the arena's [`stats`](Arena::stats), not the values in it.
+#[cfg(feature = "alloc")]
+impl<T, A: Allocator> fmt::Debug for Arena<T, A> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let stats = self.stats();
+        f.debug_struct("Arena")
+            .field("len", &stats.len)
+            .field("capacity", &stats.capacity)
+            .field("chunks", &stats.chunks)
+            .field("allocated_bytes", &stats.allocated_bytes)
+            .field("wasted_bytes", &stats.wasted_bytes)
+            .field("largest_chunk_capacity", &stats.largest_chunk_capacity)
+            .finish()
     }
 }
 
-enum IterMutState<'a, T> {
-    ChunkListRest {
-        index: usize,
-        inner_iter: slice::IterMut<'a, T>,
-    },
-    ChunkListCurrent {
-        iter: slice::IterMut<'a, T>,
-    },
-}
-
 /// Mutable arena iterator.
 ///
 /// This struct is created by the [`iter_mut`](struct.Arena.html#method.iter_mut) method on [Arenas](struct.Arena.html).
+#[cfg(feature = "alloc")]
 pub struct IterMut<'a, T: 'a> {
-    chunks: &'a mut ChunkList<T>,
-    state: IterMutState<'a, T>,
+    rest: slice::Iter<'a, RawChunk>,
+    current: Option<&'a mut [T]>,
+    inner: slice::IterMut<'a, T>,
+    remaining: usize,
 }
 
+#[cfg(feature = "alloc")]
 impl<'a, T> Iterator for IterMut<'a, T> {
     type Item = &'a mut T;
     fn next(&mut self) -> Option<&'a mut T> {
         loop {
-            self.state = match self.state {
-                IterMutState::ChunkListRest {
-                    mut index,
-                    ref mut inner_iter,
-                } => {
-                    match inner_iter.next() {
-                        Some(item) => return Some(item),
-                        None => {
-                            index += 1;
-                            if index < self.chunks.rest.len() {
-                                let inner_iter = self.chunks.rest[index].iter_mut();
-                                // Extend the lifetime of the individual elements to that of the arena.
-                                let inner_iter = unsafe { mem::transmute(inner_iter) };
-                                IterMutState::ChunkListRest { index, inner_iter }
-                            } else {
-                                let iter = self.chunks.current.iter_mut();
-                                // Extend the lifetime of the individual elements to that of the arena.
-                                let iter = unsafe { mem::transmute(iter) };
-                                IterMutState::ChunkListCurrent { iter }
-                            }
-                        }
-                    }
-                }
-                IterMutState::ChunkListCurrent { ref mut iter } => return iter.next(),
+            if let Some(item) = self.inner.next() {
+                self.remaining -= 1;
+                return Some(item);
+            }
+            self.inner = match self.rest.next() {
+                // Extend the lifetime of the individual elements to that of the arena.
+                Some(chunk) => unsafe {
+                    slice::from_raw_parts_mut(chunk.start as *mut T, chunk.len).iter_mut()
+                },
+                None => self.current.take()?.iter_mut(),
             };
         }
     }
 
     fn size_hint(&self) -> (usize, Option<usize>) {
-        let current_len = self.chunks.current.len();
-        let current_cap = self.chunks.current.capacity();
-        if self.chunks.rest.is_empty() {
-            (current_len, Some(current_len))
-        } else {
-            let rest_len = self.chunks.rest.len();
-            let last_chunk_len = self
-                .chunks
-                .rest
-                .last()
-                .map(|chunk| chunk.len())
-                .unwrap_or(0);
-
-            let min = current_len + last_chunk_len;
-            let max = min + (rest_len * current_cap / rest_len);
-
-            (min, Some(max))
-        }
+        (self.remaining, Some(self.remaining))
     }
 }
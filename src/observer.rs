@@ -0,0 +1,77 @@
+//! Hooks for observing how arenas use memory.
+
+use super::ArenaStats;
+
+/// The default size above which an `alloc_extend` call counts as large.
+pub(crate) const LARGE_EXTEND_BYTES: usize = 64 * 1024;
+
+/// Receives events about an arena's memory use.
+///
+/// Observers are installed with
+/// [`ArenaBuilder::observer`](crate::ArenaBuilder::observer), and are told
+/// the name the arena was given with
+/// [`ArenaBuilder::name`](crate::ArenaBuilder::name), if any. All methods do
+/// nothing by default, so an observer only needs to implement the events it
+/// cares about.
+///
+/// Observers are called while the arena is in the middle of an operation, so
+/// they must not use the arena they observe.
+///
+/// With the `tracing` feature, arenas also emit the same events as
+/// [`tracing`](https://docs.rs/tracing) events with the `typed_arena` target,
+/// whether or not they have an observer.
+///
+/// ## Example
+///
+/// ```
+/// use std::sync::atomic::{AtomicUsize, Ordering};
+/// use std::sync::Arc;
+/// use typed_arena::{Arena, ArenaObserver};
+///
+/// struct ChunkBytes(Arc<AtomicUsize>);
+///
+/// impl ArenaObserver for ChunkBytes {
+///     fn chunk_allocated(&self, _name: Option<&str>, bytes: usize) {
+///         self.0.fetch_add(bytes, Ordering::Relaxed);
+///     }
+/// }
+///
+/// let bytes = Arc::new(AtomicUsize::new(0));
+/// let arena: Arena<u64> = Arena::builder()
+///     .name("parser")
+///     .initial_capacity(16)
+///     .observer(ChunkBytes(bytes.clone()))
+///     .build();
+/// arena.alloc(1);
+/// assert_eq!(bytes.load(Ordering::Relaxed), 16 * 8);
+/// ```
+pub trait ArenaObserver {
+    /// Called when the arena allocates a chunk of `bytes` bytes, or takes one
+    /// from its [`ChunkPool`](crate::ChunkPool).
+    fn chunk_allocated(&self, name: Option<&str>, bytes: usize) {
+        let _ = (name, bytes);
+    }
+
+    /// Called when [`alloc_extend`](crate::Arena::alloc_extend),
+    /// [`try_alloc_extend`](crate::Arena::try_alloc_extend),
+    /// [`alloc_extend_segmented`](crate::Arena::alloc_extend_segmented) or
+    /// [`alloc_uninitialized`](crate::Arena::alloc_uninitialized) allocates at
+    /// least [`large_extend_bytes`](ArenaObserver::large_extend_bytes) bytes
+    /// of values at once.
+    fn large_extend(&self, name: Option<&str>, bytes: usize) {
+        let _ = (name, bytes);
+    }
+
+    /// The size above which an `alloc_extend` call is reported to
+    /// [`large_extend`](ArenaObserver::large_extend).
+    ///
+    /// Defaults to 64 KiB.
+    fn large_extend_bytes(&self) -> usize {
+        LARGE_EXTEND_BYTES
+    }
+
+    /// Called when the arena is dropped, with its final statistics.
+    fn dropped(&self, name: Option<&str>, stats: &ArenaStats) {
+        let _ = (name, stats);
+    }
+}
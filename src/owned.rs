@@ -0,0 +1,311 @@
+//! Arenas bundled with a value that borrows from them.
+
+#[cfg(not(feature = "std"))]
+use alloc::boxed::Box;
+#[cfg(not(feature = "std"))]
+use alloc::vec::Vec;
+
+use core::fmt;
+use core::marker::PhantomData;
+use core::mem::{self, ManuallyDrop};
+use core::ptr::{self, NonNull};
+
+use super::Arena;
+
+/// The types of the values in an [`OwnedArena`] and of its root, as functions
+/// of the lifetime of their borrow of the arena.
+///
+/// `R` stands in for an arena of `<R as ArenaRoot<'a>>::Elem` and a root of
+/// type `<R as ArenaRoot<'a>>::Of`, while the arena is borrowed for `'a`. This
+/// is implemented for `&'static T`, `&'static [T]` and `&'static str`,
+/// standing in for a root of type `&'a T`, `&'a [T]` and `&'a str` that
+/// borrows from an arena of `T`s (or bytes), and for `Option` and `Vec` of any
+/// `ArenaRoot`. For values and roots of your own, which may borrow each other,
+/// implement it on a marker type:
+///
+/// ```
+/// use std::cell::Cell;
+/// use typed_arena::ArenaRoot;
+///
+/// struct Node<'a> {
+///     next: Cell<Option<&'a Node<'a>>>,
+/// }
+///
+/// struct Graph;
+///
+/// unsafe impl<'a> ArenaRoot<'a> for Graph {
+///     type Elem = Node<'a>;
+///     type Of = Vec<&'a Node<'a>>;
+/// }
+/// ```
+///
+/// ## Safety
+///
+/// Dropping an `Elem` must not use the references of lifetime `'a` in it.
+/// The values in the arena are dropped one at a time, so those references
+/// may point to values that were already dropped. An [`Arena`] on its own
+/// rejects such types at compile time, but an `OwnedArena` can't check it.
+/// Types without a `Drop` impl of their own, or whose `Drop` impl doesn't
+/// depend on `'a`, are fine.
+pub unsafe trait ArenaRoot<'a> {
+    /// The type of the values in the arena while it is borrowed for `'a`.
+    type Elem: 'a;
+    /// The type of the root while the arena is borrowed for `'a`.
+    type Of: 'a;
+}
+
+unsafe impl<'a, T: 'static> ArenaRoot<'a> for &'static T {
+    type Elem = T;
+    type Of = &'a T;
+}
+
+unsafe impl<'a, T: 'static> ArenaRoot<'a> for &'static [T] {
+    type Elem = T;
+    type Of = &'a [T];
+}
+
+unsafe impl<'a> ArenaRoot<'a> for &'static str {
+    type Elem = u8;
+    type Of = &'a str;
+}
+
+unsafe impl<'a, R: ArenaRoot<'a>> ArenaRoot<'a> for Option<R> {
+    type Elem = R::Elem;
+    type Of = Option<R::Of>;
+}
+
+unsafe impl<'a, R: ArenaRoot<'a>> ArenaRoot<'a> for Vec<R> {
+    type Elem = R::Elem;
+    type Of = Vec<R::Of>;
+}
+
+/// The type of the values in the arena of an `OwnedArena<R>`, with the
+/// lifetime of its borrow of the arena erased.
+type Elem<R> = <R as ArenaRoot<'static>>::Elem;
+
+/// An arena together with a root value that borrows from it.
+///
+/// Values allocated in an arena borrow it, so a structure built in one can't
+/// normally be returned from the function that owns the arena, or stored in a
+/// struct next to it. An `OwnedArena` owns both, so it can be moved, returned
+/// and stored like any other value, and lends the root out through
+/// [`with_root`](OwnedArena::with_root).
+///
+/// The types of the values and of the root are given by the [`ArenaRoot`]
+/// `R`, and may both borrow from the arena, so the values can form a graph.
+/// The root is dropped before the arena and the values in it.
+///
+/// ## Example
+///
+/// ```
+/// use typed_arena::{Arena, OwnedArena};
+///
+/// fn words(text: &str) -> OwnedArena<Vec<&'static str>> {
+///     OwnedArena::new(Arena::new(), |arena| {
+///         text.split_whitespace()
+///             .map(|word| &*arena.alloc_str(word))
+///             .collect()
+///     })
+/// }
+///
+/// let words = words("a self-contained arena");
+/// words.with_root(|words| assert_eq!(words[1], "self-contained"));
+/// ```
+///
+/// References to the values can't outlive the `OwnedArena`:
+///
+/// ```compile_fail
+/// use typed_arena::{Arena, OwnedArena};
+///
+/// let numbers = OwnedArena::<&'static u32>::new(Arena::new(), |arena| arena.alloc(1));
+/// let one = numbers.with_root(|&one| one);
+/// drop(numbers);
+/// assert_eq!(*one, 1);
+/// ```
+pub struct OwnedArena<R: for<'a> ArenaRoot<'a>> {
+    // Dropped before the arena, in our `Drop` impl. The lifetime is really
+    // that of the borrow of the arena.
+    root: ManuallyDrop<<R as ArenaRoot<'static>>::Of>,
+    // Boxed so that moving the `OwnedArena` doesn't move the arena, which the
+    // root may borrow. It's a raw pointer rather than a `Box` because it's
+    // aliased by the root. The lifetime of its values is erased like that of
+    // the root.
+    arena: NonNull<Arena<Elem<R>>>,
+    _marker: PhantomData<Box<Arena<Elem<R>>>>,
+}
+
+// The root and the values may only borrow from the arena, which moves along
+// with them.
+unsafe impl<R> Send for OwnedArena<R>
+where
+    R: for<'a> ArenaRoot<'a>,
+    Elem<R>: Send,
+    <R as ArenaRoot<'static>>::Of: Send,
+{
+}
+
+impl<R: for<'a> ArenaRoot<'a>> OwnedArena<R> {
+    /// Take ownership of `arena`, and build the root from it with `f`.
+    ///
+    /// ## Example
+    ///
+    /// ```
+    /// use std::cell::Cell;
+    /// use typed_arena::{Arena, ArenaRoot, OwnedArena};
+    ///
+    /// struct Node<'a> {
+    ///     value: u32,
+    ///     next: Cell<Option<&'a Node<'a>>>,
+    /// }
+    ///
+    /// struct Ring;
+    ///
+    /// unsafe impl<'a> ArenaRoot<'a> for Ring {
+    ///     type Elem = Node<'a>;
+    ///     type Of = &'a Node<'a>;
+    /// }
+    ///
+    /// let ring = OwnedArena::<Ring>::new(Arena::new(), |arena| {
+    ///     let first = &*arena.alloc(Node { value: 1, next: Cell::new(None) });
+    ///     let second = arena.alloc(Node { value: 2, next: Cell::new(Some(first)) });
+    ///     first.next.set(Some(second));
+    ///     first
+    /// });
+    /// assert_eq!(ring.with_root(|first| first.next.get().unwrap().value), 2);
+    /// ```
+    pub fn new<F>(arena: Arena<Elem<R>>, f: F) -> OwnedArena<R>
+    where
+        F: for<'a> FnOnce(&'a Arena<<R as ArenaRoot<'a>>::Elem>) -> <R as ArenaRoot<'a>>::Of,
+    {
+        struct FreeOnPanic<T>(NonNull<Arena<T>>);
+
+        impl<T> Drop for FreeOnPanic<T> {
+            fn drop(&mut self) {
+                unsafe { drop(Box::from_raw(self.0.as_ptr())) }
+            }
+        }
+
+        let arena = NonNull::from(Box::leak(Box::new(arena)));
+        let guard = FreeOnPanic(arena);
+        let root = f(unsafe { &*(arena.as_ptr() as *const _) });
+        mem::forget(guard);
+        OwnedArena {
+            root: ManuallyDrop::new(unsafe { erase::<R>(root) }),
+            arena,
+            _marker: PhantomData,
+        }
+    }
+
+    /// Call `f` with the arena.
+    ///
+    /// Values allocated in it here can't be added to the root; use
+    /// [`with_root_mut`](OwnedArena::with_root_mut) for that.
+    pub fn with_arena<F, O>(&self, f: F) -> O
+    where
+        F: for<'a> FnOnce(&'a Arena<<R as ArenaRoot<'a>>::Elem>) -> O,
+    {
+        f(unsafe { &*(self.arena.as_ptr() as *const _) })
+    }
+
+    /// The arena, if the type of its values doesn't depend on the lifetime of
+    /// its borrow.
+    ///
+    /// Values allocated in it through this reference can't be added to the
+    /// root; use [`with_root_mut`](OwnedArena::with_root_mut) for that.
+    pub fn arena<T>(&self) -> &Arena<T>
+    where
+        R: for<'a> ArenaRoot<'a, Elem = T>,
+    {
+        unsafe { self.arena.as_ref() }
+    }
+
+    /// Call `f` with a reference to the root.
+    ///
+    /// `f` must work for any lifetime `'a`, so it can't smuggle references
+    /// with a shorter lifetime than the arena's into the root, nor return
+    /// references borrowed from it.
+    pub fn with_root<F, O>(&self, f: F) -> O
+    where
+        F: for<'a> FnOnce(&'a <R as ArenaRoot<'a>>::Of) -> O,
+    {
+        f(unsafe { &*(&*self.root as *const _ as *const _) })
+    }
+
+    /// Call `f` with the arena and a mutable reference to the root, so that
+    /// `f` can allocate new values and add them to the root.
+    ///
+    /// ## Example
+    ///
+    /// ```
+    /// use typed_arena::{Arena, OwnedArena};
+    ///
+    /// let mut list = OwnedArena::<Vec<&'static String>>::new(Arena::new(), |_| Vec::new());
+    /// list.with_root_mut(|arena, list| {
+    ///     list.push(arena.alloc("hello".to_string()));
+    /// });
+    /// assert_eq!(list.with_root(|list| list.len()), 1);
+    /// ```
+    pub fn with_root_mut<F, O>(&mut self, f: F) -> O
+    where
+        F: for<'a> FnOnce(
+            &'a Arena<<R as ArenaRoot<'a>>::Elem>,
+            &mut <R as ArenaRoot<'a>>::Of,
+        ) -> O,
+    {
+        // The root and the values can only hold on to values borrowed from
+        // the arena for `'a`, which `f` can't name outside of this call.
+        let root = &mut *self.root as *mut _ as *mut _;
+        let arena = self.arena.as_ptr() as *const _;
+        f(unsafe { &*arena }, unsafe { &mut *root })
+    }
+
+    /// Drop the root and return the arena, if the type of its values doesn't
+    /// depend on the lifetime of its borrow.
+    pub fn into_arena<T>(self) -> Arena<T>
+    where
+        R: for<'a> ArenaRoot<'a, Elem = T>,
+    {
+        let mut this = ManuallyDrop::new(self);
+        unsafe {
+            ManuallyDrop::drop(&mut this.root);
+            *Box::from_raw(this.arena.as_ptr())
+        }
+    }
+}
+
+// Changes only the lifetime of the root's type, which doesn't change its
+// layout.
+unsafe fn erase<'a, R>(root: <R as ArenaRoot<'a>>::Of) -> <R as ArenaRoot<'static>>::Of
+where
+    R: for<'b> ArenaRoot<'b>,
+{
+    let root = ManuallyDrop::new(root);
+    ptr::read(&*root as *const _ as *const <R as ArenaRoot<'static>>::Of)
+}
+
+impl<R: for<'a> ArenaRoot<'a>> Drop for OwnedArena<R> {
+    fn drop(&mut self) {
+        // Dropping the values can't observe their erased lifetime, as
+        // `ArenaRoot` promises.
+        unsafe {
+            ManuallyDrop::drop(&mut self.root);
+            drop(Box::from_raw(self.arena.as_ptr()));
+        }
+    }
+}
+
+impl<R> fmt::Debug for OwnedArena<R>
+where
+    R: for<'a> ArenaRoot<'a>,
+    for<'a> <R as ArenaRoot<'a>>::Of: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let arena = unsafe { self.arena.as_ref() };
+        self.with_root(|root| {
+            f.debug_struct("OwnedArena")
+                .field("arena", arena)
+                .field("root", root)
+                .finish()
+        })
+    }
+}
@@ -0,0 +1,127 @@
+//! Arenas whose values are pinned.
+
+use core::fmt;
+use core::mem::MaybeUninit;
+use core::pin::Pin;
+
+use super::{Arena, ArenaStats};
+
+/// An arena of values that are pinned in place.
+///
+/// Values in an arena never move while it is borrowed, but [`Arena`] can
+/// still move them afterwards, with [`into_vec`](Arena::into_vec),
+/// [`iter_mut`](Arena::iter_mut) or [`compact`](Arena::compact). A
+/// `PinnedArena` can't, so it hands out `Pin<&mut T>`, and its values stay
+/// where they are until they are dropped with the arena. This makes it a home
+/// for self-referential and intrusive types.
+///
+/// ## Example
+///
+/// ```
+/// use std::marker::PhantomPinned;
+/// use std::pin::Pin;
+/// use typed_arena::PinnedArena;
+///
+/// struct Timer {
+///     deadline: u64,
+///     _pin: PhantomPinned,
+/// }
+///
+/// let arena = PinnedArena::new();
+/// let timer: Pin<&mut Timer> = arena.alloc(Timer { deadline: 10, _pin: PhantomPinned });
+/// assert_eq!(timer.deadline, 10);
+/// ```
+pub struct PinnedArena<T> {
+    arena: Arena<T>,
+}
+
+impl<T> PinnedArena<T> {
+    /// Construct a new arena.
+    pub const fn new() -> PinnedArena<T> {
+        PinnedArena {
+            arena: Arena::new(),
+        }
+    }
+
+    /// Construct a new arena with capacity for `n` values pre-allocated.
+    pub fn with_capacity(n: usize) -> PinnedArena<T> {
+        PinnedArena {
+            arena: Arena::with_capacity(n),
+        }
+    }
+
+    /// Allocates a value in the arena, and returns a pinned reference to it.
+    #[cfg_attr(feature = "debug-callsites", track_caller)]
+    pub fn alloc(&self, value: T) -> Pin<&mut T> {
+        unsafe { Pin::new_unchecked(self.arena.alloc(value)) }
+    }
+
+    /// Allocates a value in the arena, and has `init` initialize it in place.
+    ///
+    /// This is for values that must already be pinned while they are built,
+    /// like ones that point to themselves. The arena can't allocate anything
+    /// else while `init` runs, and panics if `init` tries to. If `init`
+    /// panics, the slot is given back and nothing is dropped.
+    ///
+    /// ## Safety
+    ///
+    /// `init` must have initialized the value when it returns.
+    ///
+    /// ## Example
+    ///
+    /// ```
+    /// use std::marker::PhantomPinned;
+    /// use std::mem::MaybeUninit;
+    /// use std::pin::Pin;
+    /// use std::ptr;
+    /// use typed_arena::PinnedArena;
+    ///
+    /// struct SelfRef {
+    ///     value: u32,
+    ///     this: *const SelfRef,
+    ///     _pin: PhantomPinned,
+    /// }
+    ///
+    /// let arena = PinnedArena::new();
+    /// let node = unsafe {
+    ///     arena.alloc_with(|slot: Pin<&mut MaybeUninit<SelfRef>>| {
+    ///         let slot = slot.get_unchecked_mut().as_mut_ptr();
+    ///         ptr::write(slot, SelfRef { value: 7, this: slot, _pin: PhantomPinned });
+    ///     })
+    /// };
+    /// assert!(ptr::eq(node.this, &*node));
+    /// ```
+    #[cfg_attr(feature = "debug-callsites", track_caller)]
+    pub unsafe fn alloc_with<F>(&self, init: F) -> Pin<&mut T>
+    where
+        F: FnOnce(Pin<&mut MaybeUninit<T>>),
+    {
+        let value = self.arena.alloc_in_place(|slot| {
+            init(Pin::new_unchecked(&mut *(slot as *mut MaybeUninit<T>)));
+        });
+        Pin::new_unchecked(value)
+    }
+
+    /// Return the number of values allocated in the arena.
+    #[allow(clippy::len_without_is_empty)]
+    pub fn len(&self) -> usize {
+        self.arena.len()
+    }
+
+    /// Return statistics about the arena's memory.
+    pub fn stats(&self) -> ArenaStats {
+        self.arena.stats()
+    }
+}
+
+impl<T> Default for PinnedArena<T> {
+    fn default() -> Self {
+        PinnedArena::new()
+    }
+}
+
+impl<T> fmt::Debug for PinnedArena<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_tuple("PinnedArena").field(&self.arena).finish()
+    }
+}
@@ -0,0 +1,190 @@
+//! Chunks shared between arenas.
+
+use std::fmt;
+use std::marker::PhantomData;
+use std::mem;
+use std::ptr::NonNull;
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+
+use allocator_api2::alloc::{handle_alloc_error, Allocator, Global, Layout};
+
+use super::chunk::array_layout;
+
+/// A pool of chunks that arenas of the same type draw from and return their
+/// chunks to.
+///
+/// Cloning a `ChunkPool` creates another handle to the same pool. Arenas
+/// built with [`ArenaBuilder::chunk_pool`](crate::ArenaBuilder::chunk_pool)
+/// take a chunk from the pool whenever they need a new one, and only allocate
+/// one if the pool has no chunk that is big enough. When they are dropped or
+/// [cleared](crate::Arena::clear), they give their chunks back to the pool
+/// instead of freeing them.
+///
+/// This makes arenas that are created and dropped over and over, like one per
+/// request, stop allocating once the pool is warm. The pool retains at most
+/// `max_bytes` of chunks, and frees any chunk that is returned to it beyond
+/// that.
+///
+/// Pooled chunks are allocated from the global allocator.
+///
+/// ## Example
+///
+/// ```
+/// use typed_arena::{Arena, ChunkPool};
+///
+/// let pool = ChunkPool::new(64 * 1024);
+/// for request in 0..10 {
+///     let arena: Arena<u64> = Arena::builder().chunk_pool(pool.clone()).build();
+///     arena.alloc_extend(0..request * 100);
+/// }
+/// assert!(pool.retained_chunks() > 0);
+/// ```
+pub struct ChunkPool<T> {
+    pub(crate) raw: RawPool,
+    _marker: PhantomData<fn() -> T>,
+}
+
+impl<T> ChunkPool<T> {
+    /// Create an empty pool that retains at most `max_bytes` of chunks.
+    pub fn new(max_bytes: usize) -> ChunkPool<T> {
+        ChunkPool {
+            raw: RawPool {
+                inner: Arc::new(Mutex::new(PoolInner {
+                    layout: Layout::new::<T>(),
+                    max_bytes,
+                    bytes: 0,
+                    chunks: Vec::new(),
+                })),
+            },
+            _marker: PhantomData,
+        }
+    }
+
+    /// The number of bytes of chunks this pool retains at most.
+    pub fn max_bytes(&self) -> usize {
+        self.raw.lock().max_bytes
+    }
+
+    /// The number of bytes of chunks in the pool.
+    pub fn retained_bytes(&self) -> usize {
+        self.raw.lock().bytes
+    }
+
+    /// The number of chunks in the pool.
+    pub fn retained_chunks(&self) -> usize {
+        self.raw.lock().chunks.len()
+    }
+
+    /// Free every chunk in the pool.
+    ///
+    /// Chunks that arenas are still using are returned to the pool as usual.
+    pub fn clear(&self) {
+        self.raw.lock().clear();
+    }
+}
+
+impl<T> Clone for ChunkPool<T> {
+    fn clone(&self) -> Self {
+        ChunkPool {
+            raw: self.raw.clone(),
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<T> fmt::Debug for ChunkPool<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let inner = self.raw.lock();
+        f.debug_struct("ChunkPool")
+            .field("max_bytes", &inner.max_bytes)
+            .field("retained_bytes", &inner.bytes)
+            .field("retained_chunks", &inner.chunks.len())
+            .finish()
+    }
+}
+
+/// The type-erased part of a `ChunkPool`, which arenas hold on to.
+#[derive(Clone)]
+pub(crate) struct RawPool {
+    inner: Arc<Mutex<PoolInner>>,
+}
+
+struct PoolInner {
+    // The layout of a single element.
+    layout: Layout,
+    max_bytes: usize,
+    bytes: usize,
+    // Sorted by capacity, so that arenas get the smallest chunk that fits.
+    chunks: Vec<PooledChunk>,
+}
+
+struct PooledChunk {
+    start: NonNull<u8>,
+    capacity: usize,
+}
+
+// The pool owns the memory of its chunks, which holds no values.
+unsafe impl Send for PooledChunk {}
+
+impl RawPool {
+    /// Take the smallest chunk with room for between `min` and `max`
+    /// elements, or allocate one with room for `min` elements if there isn't
+    /// any. Returns the start of the chunk and its capacity.
+    pub(crate) fn take(&self, min: usize, max: usize) -> (*mut u8, usize) {
+        let mut inner = self.lock();
+        let index = inner.chunks.partition_point(|chunk| chunk.capacity < min);
+        if index < inner.chunks.len() && inner.chunks[index].capacity <= max {
+            let chunk = inner.chunks.remove(index);
+            inner.bytes -= array_layout(inner.layout, chunk.capacity).size();
+            return (chunk.start.as_ptr(), chunk.capacity);
+        }
+        let layout = array_layout(inner.layout, min);
+        drop(inner);
+        match Global.allocate(layout) {
+            Ok(start) => (start.as_ptr() as *mut u8, min),
+            Err(_) => handle_alloc_error(layout),
+        }
+    }
+
+    /// Return an empty chunk to the pool, or free it if the pool is full.
+    ///
+    /// The chunk must have come from `take`.
+    pub(crate) unsafe fn give(&self, start: *mut u8, capacity: usize) {
+        let mut inner = self.lock();
+        let layout = array_layout(inner.layout, capacity);
+        let start = NonNull::new_unchecked(start);
+        if inner.max_bytes - inner.bytes < layout.size() {
+            drop(inner);
+            Global.deallocate(start, layout);
+            return;
+        }
+        inner.bytes += layout.size();
+        let index = inner
+            .chunks
+            .partition_point(|chunk| chunk.capacity < capacity);
+        inner.chunks.insert(index, PooledChunk { start, capacity });
+    }
+
+    // A panic while the lock is held can't leave the pool inconsistent, so
+    // poisoning is ignored.
+    fn lock(&self) -> MutexGuard<'_, PoolInner> {
+        self.inner.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+impl PoolInner {
+    fn clear(&mut self) {
+        self.bytes = 0;
+        for chunk in mem::take(&mut self.chunks) {
+            unsafe {
+                Global.deallocate(chunk.start, array_layout(self.layout, chunk.capacity));
+            }
+        }
+    }
+}
+
+impl Drop for PoolInner {
+    fn drop(&mut self) {
+        self.clear();
+    }
+}
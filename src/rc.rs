@@ -0,0 +1,292 @@
+//! Arenas whose values keep the arena alive, instead of borrowing it.
+
+#[cfg(not(feature = "std"))]
+use alloc::rc::Rc;
+#[cfg(feature = "std")]
+use std::rc::Rc;
+#[cfg(feature = "std")]
+use std::sync::Arc;
+
+use core::fmt;
+use core::ops::Deref;
+use core::ptr::NonNull;
+
+use super::Arena;
+#[cfg(feature = "std")]
+use super::SyncArena;
+
+/// A reference-counted arena, whose allocations return [`ArenaRc`] handles.
+///
+/// Each handle keeps the whole arena alive, so values can be allocated
+/// without borrowing the arena, and be stored anywhere. Cloning an `RcArena`
+/// creates another handle to the same arena. The arena and its values are
+/// dropped once the last `RcArena` and `ArenaRc` pointing to it are gone.
+///
+/// A value that holds an `ArenaRc` into its own arena keeps that arena alive
+/// forever, just like a cycle of `Rc`s. Values that reference each other
+/// should use an [`Arena`] and plain references.
+///
+/// ## Example
+///
+/// ```
+/// use typed_arena::{ArenaRc, RcArena};
+///
+/// fn names() -> Vec<ArenaRc<String>> {
+///     let arena = RcArena::new();
+///     vec![arena.alloc("Ferris".to_string()), arena.alloc("Corro".to_string())]
+/// }
+///
+/// let names = names();
+/// assert_eq!(*names[0], "Ferris");
+/// ```
+pub struct RcArena<T> {
+    arena: Rc<Arena<T>>,
+}
+
+impl<T> RcArena<T> {
+    /// Construct a new arena.
+    pub fn new() -> RcArena<T> {
+        RcArena::from(Arena::new())
+    }
+
+    /// Construct a new arena with capacity for `n` values pre-allocated.
+    pub fn with_capacity(n: usize) -> RcArena<T> {
+        RcArena::from(Arena::with_capacity(n))
+    }
+
+    /// Allocates a value in the arena, and returns a handle to it that keeps
+    /// the arena alive.
+    #[cfg_attr(feature = "debug-callsites", track_caller)]
+    pub fn alloc(&self, value: T) -> ArenaRc<T> {
+        ArenaRc {
+            value: NonNull::from(self.arena.alloc(value)),
+            arena: self.arena.clone(),
+        }
+    }
+
+    /// The arena.
+    pub fn arena(&self) -> &Arena<T> {
+        &self.arena
+    }
+}
+
+impl<T> Clone for RcArena<T> {
+    fn clone(&self) -> Self {
+        RcArena {
+            arena: self.arena.clone(),
+        }
+    }
+}
+
+impl<T> Default for RcArena<T> {
+    fn default() -> Self {
+        RcArena::new()
+    }
+}
+
+impl<T> From<Arena<T>> for RcArena<T> {
+    fn from(arena: Arena<T>) -> Self {
+        RcArena {
+            arena: Rc::new(arena),
+        }
+    }
+}
+
+impl<T> fmt::Debug for RcArena<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_tuple("RcArena").field(&self.arena).finish()
+    }
+}
+
+/// A handle to a value in an [`RcArena`], which keeps the arena alive.
+///
+/// This derefs to the value. Cloning it creates another handle to the same
+/// value.
+pub struct ArenaRc<T> {
+    // Points into `arena`, whose values never move.
+    value: NonNull<T>,
+    arena: Rc<Arena<T>>,
+}
+
+impl<T> ArenaRc<T> {
+    /// Return the arena the value is in, to allocate more values in it.
+    pub fn arena(this: &ArenaRc<T>) -> RcArena<T> {
+        RcArena {
+            arena: this.arena.clone(),
+        }
+    }
+
+    /// Return true if both handles point to the same value.
+    pub fn ptr_eq(this: &ArenaRc<T>, other: &ArenaRc<T>) -> bool {
+        this.value == other.value
+    }
+}
+
+impl<T> Clone for ArenaRc<T> {
+    fn clone(&self) -> Self {
+        ArenaRc {
+            value: self.value,
+            arena: self.arena.clone(),
+        }
+    }
+}
+
+impl<T> Deref for ArenaRc<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        unsafe { self.value.as_ref() }
+    }
+}
+
+impl<T: fmt::Debug> fmt::Debug for ArenaRc<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Debug::fmt(&**self, f)
+    }
+}
+
+/// A thread-safe reference-counted arena, whose allocations return
+/// [`ArenaArc`] handles.
+///
+/// This is the thread-safe counterpart of [`RcArena`], backed by a
+/// [`SyncArena`]. Its handles can be sent to other threads and tasks when `T`
+/// is `Send` and `Sync`.
+///
+/// ## Example
+///
+/// ```
+/// use std::thread;
+/// use typed_arena::ArcArena;
+///
+/// let arena = ArcArena::new();
+/// let greeting = arena.alloc("hello".to_string());
+/// drop(arena);
+///
+/// let len = thread::spawn(move || greeting.len()).join().unwrap();
+/// assert_eq!(len, 5);
+/// ```
+#[cfg(feature = "std")]
+pub struct ArcArena<T> {
+    arena: Arc<SyncArena<T>>,
+}
+
+#[cfg(feature = "std")]
+impl<T> ArcArena<T> {
+    /// Construct a new arena.
+    pub fn new() -> ArcArena<T> {
+        ArcArena::from(Arena::new())
+    }
+
+    /// Construct a new arena with capacity for `n` values pre-allocated.
+    pub fn with_capacity(n: usize) -> ArcArena<T> {
+        ArcArena::from(Arena::with_capacity(n))
+    }
+
+    /// Allocates a value in the arena, and returns a handle to it that keeps
+    /// the arena alive.
+    #[cfg_attr(feature = "debug-callsites", track_caller)]
+    pub fn alloc(&self, value: T) -> ArenaArc<T> {
+        ArenaArc {
+            value: NonNull::from(self.arena.alloc(value)),
+            arena: self.arena.clone(),
+        }
+    }
+
+    /// The arena.
+    pub fn arena(&self) -> &SyncArena<T> {
+        &self.arena
+    }
+}
+
+#[cfg(feature = "std")]
+impl<T> Clone for ArcArena<T> {
+    fn clone(&self) -> Self {
+        ArcArena {
+            arena: self.arena.clone(),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl<T> Default for ArcArena<T> {
+    fn default() -> Self {
+        ArcArena::new()
+    }
+}
+
+#[cfg(feature = "std")]
+impl<T> From<Arena<T>> for ArcArena<T> {
+    fn from(arena: Arena<T>) -> Self {
+        ArcArena {
+            arena: Arc::new(SyncArena::from(arena)),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl<T> fmt::Debug for ArcArena<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("ArcArena")
+            .field("len", &self.arena.len())
+            .finish()
+    }
+}
+
+/// A handle to a value in an [`ArcArena`], which keeps the arena alive.
+///
+/// This derefs to the value. Cloning it creates another handle to the same
+/// value.
+#[cfg(feature = "std")]
+pub struct ArenaArc<T> {
+    // Points into `arena`, whose values never move.
+    value: NonNull<T>,
+    arena: Arc<SyncArena<T>>,
+}
+
+// Handles share the value between threads, and the last one may drop the
+// whole arena on any thread.
+#[cfg(feature = "std")]
+unsafe impl<T: Send + Sync> Send for ArenaArc<T> {}
+#[cfg(feature = "std")]
+unsafe impl<T: Send + Sync> Sync for ArenaArc<T> {}
+
+#[cfg(feature = "std")]
+impl<T> ArenaArc<T> {
+    /// Return the arena the value is in, to allocate more values in it.
+    pub fn arena(this: &ArenaArc<T>) -> ArcArena<T> {
+        ArcArena {
+            arena: this.arena.clone(),
+        }
+    }
+
+    /// Return true if both handles point to the same value.
+    pub fn ptr_eq(this: &ArenaArc<T>, other: &ArenaArc<T>) -> bool {
+        this.value == other.value
+    }
+}
+
+#[cfg(feature = "std")]
+impl<T> Clone for ArenaArc<T> {
+    fn clone(&self) -> Self {
+        ArenaArc {
+            value: self.value,
+            arena: self.arena.clone(),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl<T> Deref for ArenaArc<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        unsafe { self.value.as_ref() }
+    }
+}
+
+#[cfg(feature = "std")]
+impl<T: fmt::Debug> fmt::Debug for ArenaArc<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Debug::fmt(&**self, f)
+    }
+}
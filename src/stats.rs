@@ -0,0 +1,42 @@
+//! Introspection of an arena's memory.
+
+/// A snapshot of how an arena uses its memory.
+///
+/// Returned by [`Arena::stats`](crate::Arena::stats). Capacities are counted
+/// in elements and sizes in bytes. For zero-sized types, the capacity of a
+/// chunk is unbounded and reported as `usize::MAX`.
+///
+/// ## Example
+///
+/// ```
+/// use typed_arena::Arena;
+///
+/// let arena: Arena<u32> = Arena::with_capacity(8);
+/// arena.alloc_extend(0..6);
+/// arena.alloc_extend(0..4);
+///
+/// let stats = arena.stats();
+/// assert_eq!(stats.len, 10);
+/// assert_eq!(stats.chunks, 2);
+/// assert_eq!(stats.wasted_bytes, 2 * 4);
+/// ```
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct ArenaStats {
+    /// The number of values in the arena.
+    pub len: usize,
+    /// The number of values the arena's chunks can hold in total.
+    pub capacity: usize,
+    /// The number of chunks the arena has allocated.
+    pub chunks: usize,
+    /// The size of all chunks.
+    pub allocated_bytes: usize,
+    /// The size of the spare capacity the arena left behind in chunks it no
+    /// longer allocates from.
+    ///
+    /// With [backfilling](crate::ArenaBuilder::backfill), the arena still
+    /// allocates from this space.
+    pub wasted_bytes: usize,
+    /// The capacity of the largest chunk.
+    pub largest_chunk_capacity: usize,
+}
@@ -0,0 +1,173 @@
+//! An arena that can be shared between threads.
+
+use std::sync::{Mutex, MutexGuard, PoisonError};
+
+use super::{Arena, IterMut};
+
+/// An arena of objects of type `T` that can be shared between threads.
+///
+/// This is an [`Arena`] behind a lock, which is only held while the arena
+/// hands out a slot. Since [`SyncArena::new`] is a `const fn`, it can be used
+/// to initialize a `static`, and like [`Arena::new`] it doesn't allocate
+/// anything until the first value is allocated.
+///
+/// ## Example
+///
+/// ```
+/// use std::thread;
+/// use typed_arena::SyncArena;
+///
+/// static NAMES: SyncArena<String> = SyncArena::new();
+///
+/// let handles: Vec<_> = (0..4)
+///     .map(|i| thread::spawn(move || NAMES.alloc(format!("thread {}", i)).len()))
+///     .collect();
+/// for handle in handles {
+///     assert_eq!(handle.join().unwrap(), 8);
+/// }
+/// assert_eq!(NAMES.len(), 4);
+/// ```
+pub struct SyncArena<T> {
+    arena: Mutex<Arena<T>>,
+}
+
+impl<T> SyncArena<T> {
+    /// Construct a new arena.
+    ///
+    /// ## Example
+    ///
+    /// ```
+    /// use typed_arena::SyncArena;
+    ///
+    /// static ARENA: SyncArena<u32> = SyncArena::new();
+    /// # ARENA.alloc(1);
+    /// ```
+    pub const fn new() -> SyncArena<T> {
+        SyncArena {
+            arena: Mutex::new(Arena::new()),
+        }
+    }
+
+    /// Construct a new arena with capacity for `n` values pre-allocated.
+    ///
+    /// ## Example
+    ///
+    /// ```
+    /// use typed_arena::SyncArena;
+    ///
+    /// let arena = SyncArena::with_capacity(1337);
+    /// # arena.alloc(1);
+    /// ```
+    pub fn with_capacity(n: usize) -> SyncArena<T> {
+        SyncArena::from(Arena::with_capacity(n))
+    }
+
+    /// Allocates a value in the arena, and returns a mutable reference
+    /// to that value.
+    ///
+    /// ## Example
+    ///
+    /// ```
+    /// use typed_arena::SyncArena;
+    ///
+    /// let arena = SyncArena::new();
+    /// let x = arena.alloc(42);
+    /// assert_eq!(*x, 42);
+    /// ```
+    #[cfg_attr(feature = "debug-callsites", track_caller)]
+    pub fn alloc(&self, value: T) -> &mut T {
+        let slot: *mut T = self.lock().alloc(value);
+        // Values never move once they are allocated, so they stay valid after
+        // the lock is released, for as long as the arena is borrowed.
+        unsafe { &mut *slot }
+    }
+
+    /// Uses the contents of an iterator to allocate values in the arena.
+    /// Returns a mutable slice that contains these values.
+    ///
+    /// The iterator is collected into a temporary `Vec` before the arena is
+    /// locked, so it may allocate from this arena itself. That costs a heap
+    /// allocation and a copy of the values on every call, even when the
+    /// length of the iterator is known up front; use [`alloc`](SyncArena::alloc)
+    /// in a loop, or lock an [`Arena`] yourself, to avoid them.
+    ///
+    /// ## Example
+    ///
+    /// ```
+    /// use typed_arena::SyncArena;
+    ///
+    /// let arena = SyncArena::new();
+    /// let abc = arena.alloc_extend("abcdefg".chars().take(3));
+    /// assert_eq!(abc, ['a', 'b', 'c']);
+    /// ```
+    #[cfg_attr(feature = "debug-callsites", track_caller)]
+    pub fn alloc_extend<I>(&self, iterable: I) -> &mut [T]
+    where
+        I: IntoIterator<Item = T>,
+    {
+        let values: Vec<T> = iterable.into_iter().collect();
+        let slice: *mut [T] = self.lock().alloc_extend(values);
+        unsafe { &mut *slice }
+    }
+
+    /// Return the number of values allocated in the arena.
+    ///
+    /// ## Example
+    ///
+    /// ```
+    /// use typed_arena::SyncArena;
+    ///
+    /// let arena = SyncArena::new();
+    /// arena.alloc(1);
+    /// arena.alloc(2);
+    /// assert_eq!(arena.len(), 2);
+    /// ```
+    #[allow(clippy::len_without_is_empty)]
+    pub fn len(&self) -> usize {
+        self.lock().len()
+    }
+
+    /// Returns an iterator that allows modifying each value.
+    ///
+    /// See [`Arena::iter_mut`].
+    pub fn iter_mut(&mut self) -> IterMut<'_, T> {
+        self.arena
+            .get_mut()
+            .unwrap_or_else(PoisonError::into_inner)
+            .iter_mut()
+    }
+
+    /// Convert this `SyncArena` into a `Vec<T>`.
+    ///
+    /// See [`Arena::into_vec`].
+    pub fn into_vec(self) -> Vec<T> {
+        self.into_inner().into_vec()
+    }
+
+    /// Unwrap the [`Arena`] this arena is made of.
+    pub fn into_inner(self) -> Arena<T> {
+        self.arena
+            .into_inner()
+            .unwrap_or_else(PoisonError::into_inner)
+    }
+
+    // A panic while the lock is held can't leave the arena inconsistent, so
+    // poisoning is ignored.
+    fn lock(&self) -> MutexGuard<'_, Arena<T>> {
+        self.arena.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+impl<T> Default for SyncArena<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> From<Arena<T>> for SyncArena<T> {
+    fn from(arena: Arena<T>) -> Self {
+        SyncArena {
+            arena: Mutex::new(arena),
+        }
+    }
+}
@@ -1,6 +1,6 @@
 use super::*;
-use std::cell::Cell;
-use std::mem;
+use std::cell::{Cell, RefCell};
+use std::mem::{self, MaybeUninit};
 use std::panic::{self, AssertUnwindSafe};
 use std::ptr;
 
@@ -11,6 +11,7 @@ impl<'a> Drop for DropTracker<'a> {
     }
 }
 
+#[allow(dead_code)]
 struct Node<'a, 'b: 'a>(Option<&'a Node<'a, 'b>>, u32, DropTracker<'b>);
 
 #[test]
@@ -20,16 +21,16 @@ fn arena_as_intended() {
         let arena = Arena::with_capacity(2);
 
         let mut node: &Node = arena.alloc(Node(None, 1, DropTracker(&drop_counter)));
-        assert_eq!(arena.chunks.borrow().rest.len(), 0);
+        assert_eq!(arena.chunks.inner.borrow().rest.len(), 0);
 
         node = arena.alloc(Node(Some(node), 2, DropTracker(&drop_counter)));
-        assert_eq!(arena.chunks.borrow().rest.len(), 0);
+        assert_eq!(arena.chunks.inner.borrow().rest.len(), 0);
 
         node = arena.alloc(Node(Some(node), 3, DropTracker(&drop_counter)));
-        assert_eq!(arena.chunks.borrow().rest.len(), 1);
+        assert_eq!(arena.chunks.inner.borrow().rest.len(), 1);
 
         node = arena.alloc(Node(Some(node), 4, DropTracker(&drop_counter)));
-        assert_eq!(arena.chunks.borrow().rest.len(), 1);
+        assert_eq!(arena.chunks.inner.borrow().rest.len(), 1);
 
         assert_eq!(node.1, 4);
         assert_eq!(node.0.unwrap().1, 3);
@@ -39,17 +40,16 @@ fn arena_as_intended() {
 
         assert_eq!(arena.len(), 4);
 
-        mem::drop(node);
         assert_eq!(drop_counter.get(), 0);
 
         let mut node: &Node = arena.alloc(Node(None, 5, DropTracker(&drop_counter)));
-        assert_eq!(arena.chunks.borrow().rest.len(), 1);
+        assert_eq!(arena.chunks.inner.borrow().rest.len(), 1);
 
         node = arena.alloc(Node(Some(node), 6, DropTracker(&drop_counter)));
-        assert_eq!(arena.chunks.borrow().rest.len(), 1);
+        assert_eq!(arena.chunks.inner.borrow().rest.len(), 1);
 
         node = arena.alloc(Node(Some(node), 7, DropTracker(&drop_counter)));
-        assert_eq!(arena.chunks.borrow().rest.len(), 2);
+        assert_eq!(arena.chunks.inner.borrow().rest.len(), 2);
 
         assert_eq!(drop_counter.get(), 0);
 
@@ -111,7 +111,7 @@ fn test_alloc_uninitialized() {
             assert_eq!(drop_counter.get(), 0);
         }
     }
-    assert_eq!(drop_counter.get(), (0..LIMIT).fold(0, |a, e| a + e) as u32);
+    assert_eq!(drop_counter.get(), (0..LIMIT).sum::<usize>() as u32);
 }
 
 #[test]
@@ -167,7 +167,7 @@ fn alloc_uninitialized_with_panic() {
         let arena: Arena<Dropper> = Arena::new();
         arena.reserve_extend(2);
         let uninitialized = arena.uninitialized_array();
-        assert!((*uninitialized).len() >= 2);
+        assert!((&*uninitialized).len() >= 2);
         ptr::write((*uninitialized)[0].as_mut_ptr(), Dropper(false));
         reached_first_init = true;
         panic!("To drop the arena");
@@ -196,8 +196,6 @@ fn test_uninitialized_array() {
 
 #[test]
 fn dont_trust_the_iterator_size() {
-    use std::iter::repeat;
-
     struct WrongSizeIter<I>(I);
     impl<I> Iterator for WrongSizeIter<I>
     where
@@ -218,10 +216,55 @@ fn dont_trust_the_iterator_size() {
 
     let arena = Arena::with_capacity(2);
     arena.alloc(0);
-    let slice = arena.alloc_extend(WrongSizeIter(repeat(1).take(1_000)));
+    let slice = arena.alloc_extend(WrongSizeIter((0..1_000).map(|_| 1)));
     // Allocation of 1000 elements should have created a new chunk
-    assert_eq!(arena.chunks.borrow().rest.len(), 1);
+    assert_eq!(arena.chunks.inner.borrow().rest.len(), 1);
     assert_eq!(slice.len(), 1000);
+
+    // The current chunk is already full when the first item arrives.
+    let arena = Arena::with_capacity(2);
+    arena.alloc_extend(0..2);
+    let slice = arena.alloc_extend(WrongSizeIter(2..5));
+    assert_eq!(slice, [2, 3, 4]);
+    assert_eq!(arena.into_vec(), [0, 1, 2, 3, 4]);
+}
+
+#[test]
+fn alloc_extend_grows_geometrically_under_any_policy() {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    struct CountChunks(Arc<AtomicUsize>);
+    impl ArenaObserver for CountChunks {
+        fn chunk_allocated(&self, _name: Option<&str>, _bytes: usize) {
+            self.0.fetch_add(1, Ordering::SeqCst);
+        }
+    }
+
+    let chunks = Arc::new(AtomicUsize::new(0));
+    let arena = Arena::builder()
+        .growth(Fixed(64))
+        .max_chunk_capacity(64)
+        .observer(CountChunks(chunks.clone()))
+        .build();
+    let slice = arena.alloc_extend((0..10_000).filter(|_| true));
+    assert_eq!(slice.len(), 10_000);
+    assert!(slice.iter().copied().eq(0..10_000));
+    // 64, then doubling up to 10 000.
+    assert_eq!(chunks.load(Ordering::SeqCst), 9);
+}
+
+#[test]
+fn alloc_extend_after_a_full_chunk() {
+    let arena = Arena::with_capacity(4);
+    for i in 0..4 {
+        arena.alloc(i);
+    }
+    // `filter` has a lower size hint of 0, so nothing is reserved up front
+    // and the first item finds the current chunk full.
+    let slice = arena.alloc_extend((100..103).filter(|_| true));
+    assert_eq!(slice, [100, 101, 102]);
+    assert_eq!(arena.into_vec(), [0, 1, 2, 3, 100, 101, 102]);
 }
 
 #[test]
@@ -250,7 +293,7 @@ fn iter_mut_low_capacity() {
     }
 
     assert!(
-        arena.chunks.borrow().rest.len() > 1,
+        arena.chunks.inner.borrow().rest.len() > 1,
         "expected multiple chunks"
     );
 
@@ -276,7 +319,7 @@ fn iter_mut_high_capacity() {
     }
 
     assert!(
-        arena.chunks.borrow().rest.is_empty(),
+        arena.chunks.inner.borrow().rest.is_empty(),
         "expected single chunk"
     );
 
@@ -391,3 +434,1433 @@ fn size_hint_many_items() {
         assert_size_hint(i, iter);
     }
 }
+
+#[test]
+fn first_chunk_is_allocated_lazily() {
+    thread_local! {
+        static ARENA: Arena<u64> = const { Arena::new() };
+    }
+    ARENA.with(|arena| {
+        assert_eq!(chunk_capacities(arena), [0]);
+        arena.alloc(1);
+        assert_eq!(chunk_capacities(arena), [INITIAL_SIZE / 8]);
+    });
+
+    let arena: Arena<u64> = Arena::new();
+    assert_eq!(chunk_capacities(&arena), [0]);
+    assert_eq!(arena.len(), 0);
+    assert_eq!(arena.into_vec(), []);
+
+    let mut arena: Arena<u64> = Arena::builder().initial_capacity(8).build();
+    assert_eq!(arena.iter_mut().count(), 0);
+    arena.alloc_extend(0..20);
+    assert_eq!(chunk_capacities(&arena), [20]);
+    arena.alloc(20);
+    assert_eq!(chunk_capacities(&arena), [20, 40]);
+}
+
+fn chunk_capacities<T>(arena: &Arena<T>) -> Vec<usize> {
+    let chunks = arena.chunks.inner.borrow();
+    let mut capacities: Vec<usize> = chunks.rest.iter().map(|chunk| chunk.capacity).collect();
+    capacities.push(chunks.capacity);
+    capacities
+}
+
+#[test]
+fn builder_initial_size() {
+    let arena: Arena<u32> = Arena::builder().initial_capacity(10).build();
+    arena.alloc(0);
+    assert_eq!(chunk_capacities(&arena), [10]);
+
+    let arena: Arena<u32> = Arena::builder().initial_bytes(64).build();
+    arena.alloc(0);
+    assert_eq!(chunk_capacities(&arena), [16]);
+
+    let arena: Arena<u32> = Arena::builder().build();
+    arena.alloc(0);
+    assert_eq!(chunk_capacities(&arena), [INITIAL_SIZE / 4]);
+}
+
+#[test]
+fn growth_policies() {
+    let arena = Arena::builder()
+        .initial_capacity(1)
+        .growth(CappedDoubling { max: 8 })
+        .build();
+    for i in 0..40 {
+        arena.alloc(i);
+    }
+    assert_eq!(chunk_capacities(&arena), [1, 2, 4, 8, 8, 8, 8, 8]);
+
+    let arena = Arena::builder()
+        .initial_capacity(4)
+        .growth(Fixed(4))
+        .build();
+    for i in 0..12 {
+        arena.alloc(i);
+    }
+    // A request larger than the fixed size still gets a contiguous chunk.
+    assert_eq!(arena.alloc_extend(0..6).len(), 6);
+    assert_eq!(chunk_capacities(&arena), [4, 4, 4, 6]);
+
+    let arena = Arena::builder()
+        .initial_capacity(2)
+        .growth(|current: usize, _required: usize| current + 3)
+        .build();
+    for i in 0..20 {
+        arena.alloc(i);
+    }
+    assert_eq!(chunk_capacities(&arena), [2, 5, 8, 11]);
+}
+
+#[test]
+fn max_chunk_size() {
+    let arena = Arena::builder()
+        .initial_capacity(100)
+        .max_chunk_capacity(16)
+        .build();
+    for i in 0..40 {
+        arena.alloc(i);
+    }
+    assert_eq!(chunk_capacities(&arena), [16, 16, 16]);
+    assert_eq!(arena.alloc_extend(0..20).len(), 20);
+
+    let arena: Arena<u64> = Arena::builder().max_chunk_bytes(64).build();
+    arena.alloc(0);
+    arena.alloc_extend(1..20);
+    assert_eq!(chunk_capacities(&arena), [8, 19]);
+    assert_eq!(arena.into_vec(), (0..20).collect::<Vec<_>>());
+}
+
+fn wasted_tails<T>(arena: &Arena<T>) -> usize {
+    let chunks = arena.chunks.inner.borrow();
+    chunks
+        .rest
+        .iter()
+        .map(|chunk| chunk.capacity - chunk.len)
+        .sum()
+}
+
+#[test]
+fn backfill_reduces_waste() {
+    fn workload(arena: &Arena<usize>) {
+        for i in 0..200 {
+            arena.alloc(i);
+            if i % 10 == 0 {
+                arena.alloc_extend(0..i);
+            }
+        }
+    }
+
+    let plain = Arena::builder().initial_capacity(8).build();
+    workload(&plain);
+    let backfilled = Arena::builder().initial_capacity(8).backfill(true).build();
+    workload(&backfilled);
+
+    assert_eq!(plain.len(), backfilled.len());
+    assert!(wasted_tails(&plain) > 0);
+    // Backfilling needs less memory to hold the same values.
+    let capacity = |arena: &Arena<usize>| chunk_capacities(arena).iter().sum::<usize>();
+    assert!(capacity(&backfilled) < capacity(&plain));
+
+    let mut plain = plain.into_vec();
+    let mut backfilled = backfilled.into_vec();
+    plain.sort();
+    backfilled.sort();
+    assert_eq!(plain, backfilled);
+}
+
+#[test]
+fn backfill_fills_tails_before_growing() {
+    let drop_counter = Cell::new(0);
+    {
+        let arena = Arena::builder().initial_capacity(4).backfill(true).build();
+        arena.alloc(Node(None, 0, DropTracker(&drop_counter)));
+        arena.reserve_extend(8);
+        assert_eq!(wasted_tails(&arena), 3);
+
+        let mut node: &Node = arena.alloc(Node(None, 1, DropTracker(&drop_counter)));
+        for i in 2..12 {
+            node = arena.alloc(Node(Some(node), i, DropTracker(&drop_counter)));
+        }
+        assert_eq!(wasted_tails(&arena), 0);
+        assert_eq!(chunk_capacities(&arena), [4, 8]);
+        assert_eq!(arena.len(), 12);
+        assert_eq!(node.1, 11);
+    }
+    assert_eq!(drop_counter.get(), 12);
+}
+
+#[test]
+fn alloc_extend_segmented_never_moves() {
+    struct WrongSizeIter<I>(I);
+    impl<I: Iterator> Iterator for WrongSizeIter<I> {
+        type Item = I::Item;
+
+        fn next(&mut self) -> Option<Self::Item> {
+            self.0.next()
+        }
+
+        fn size_hint(&self) -> (usize, Option<usize>) {
+            (0, Some(0))
+        }
+    }
+
+    let arena = Arena::with_capacity(2);
+    arena.alloc(0);
+    let start = unsafe { (&*arena.uninitialized_array()).as_ptr() } as *const usize;
+    let segments = arena.alloc_extend_segmented(WrongSizeIter(1..1_000));
+
+    // The first item stays where it was pushed, in the first chunk.
+    assert_eq!(segments[0].as_ptr(), start);
+    assert_eq!(segments[0], [1]);
+    assert!(segments.len() > 2);
+    assert!(segments.iter().all(|segment| !segment.is_empty()));
+    let items: Vec<usize> = segments.iter().flat_map(|s| s.iter().cloned()).collect();
+    assert_eq!(items, (1..1_000).collect::<Vec<_>>());
+    assert_eq!(arena.len(), 1_000);
+}
+
+#[test]
+fn alloc_extend_segmented_edge_cases() {
+    let arena = Arena::with_capacity(4);
+    assert!(arena.alloc_extend_segmented(0..0).is_empty());
+    assert_eq!(arena.alloc_extend_segmented(0..4), [&mut [0, 1, 2, 3][..]]);
+
+    // The current chunk is full, so the first segment starts a new one.
+    let segments = arena.alloc_extend_segmented(4..6);
+    assert_eq!(segments, [&mut [4, 5][..]]);
+    assert_eq!(arena.chunks.inner.borrow().rest.len(), 1);
+
+    let drop_counter = Cell::new(0);
+    {
+        let arena = Arena::with_capacity(3);
+        let iter = (0..10).map(|j| Node(None, j, DropTracker(&drop_counter)));
+        let segments = arena.alloc_extend_segmented(iter);
+        assert_eq!(segments.iter().map(|s| s.len()).sum::<usize>(), 10);
+    }
+    assert_eq!(drop_counter.get(), 10);
+}
+
+#[test]
+fn zero_sized_values() {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    static DROPS: AtomicUsize = AtomicUsize::new(0);
+    #[derive(Debug, PartialEq)]
+    struct Zst;
+    impl Drop for Zst {
+        fn drop(&mut self) {
+            DROPS.fetch_add(1, Ordering::SeqCst);
+        }
+    }
+
+    let mut arena = Arena::with_capacity(0);
+    for _ in 0..100 {
+        arena.alloc(Zst);
+    }
+    assert_eq!(arena.alloc_extend((0..50).map(|_| Zst)).len(), 50);
+    assert_eq!(arena.len(), 150);
+    assert_eq!(arena.iter_mut().count(), 150);
+    assert_eq!(DROPS.load(Ordering::SeqCst), 0);
+    drop(arena);
+    assert_eq!(DROPS.load(Ordering::SeqCst), 150);
+
+    let arena = Arena::new();
+    arena.alloc_extend((0..10).map(|_| Zst));
+    let vec = arena.into_vec();
+    assert_eq!(vec.len(), 10);
+    assert_eq!(DROPS.load(Ordering::SeqCst), 150);
+    drop(vec);
+    assert_eq!(DROPS.load(Ordering::SeqCst), 160);
+}
+
+#[test]
+fn clear_and_drop_run_destructors_only_when_needed() {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    let drop_counter = Cell::new(0);
+    let mut arena = Arena::with_capacity(2);
+    for _ in 0..100 {
+        arena.alloc(DropTracker(&drop_counter));
+    }
+    arena.alloc_extend((0..50).map(|_| DropTracker(&drop_counter)));
+    assert!(arena.stats().chunks > 1);
+    arena.clear();
+    assert_eq!(drop_counter.get(), 150);
+    // Outgrow the chunk that `clear` kept.
+    for _ in 0..300 {
+        arena.alloc(DropTracker(&drop_counter));
+    }
+    assert!(arena.stats().chunks > 1);
+    drop(arena);
+    assert_eq!(drop_counter.get(), 450);
+
+    static DROPS: AtomicUsize = AtomicUsize::new(0);
+    struct Zst;
+    impl Drop for Zst {
+        fn drop(&mut self) {
+            DROPS.fetch_add(1, Ordering::SeqCst);
+        }
+    }
+
+    let mut arena = Arena::new();
+    arena.alloc_extend((0..10).map(|_| Zst));
+    arena.clear();
+    assert_eq!(DROPS.load(Ordering::SeqCst), 10);
+    arena.alloc(Zst);
+    drop(arena);
+    assert_eq!(DROPS.load(Ordering::SeqCst), 11);
+
+    // Values that don't need dropping are skipped, but still cleared.
+    let mut arena = Arena::with_capacity(2);
+    arena.alloc_extend(0..100u64);
+    arena.clear();
+    assert_eq!(arena.len(), 0);
+    assert_eq!(arena.alloc_extend(0..3u64), [0, 1, 2]);
+}
+
+#[test]
+fn alloc_extend_reentrant_allocation_panics() {
+    let drop_counter = Cell::new(0);
+    {
+        let arena = Arena::with_capacity(16);
+        let result = panic::catch_unwind(AssertUnwindSafe(|| {
+            arena.alloc_extend((0..4).map(|i| {
+                if i == 2 {
+                    arena.alloc(Node(None, 100, DropTracker(&drop_counter)));
+                }
+                Node(None, i, DropTracker(&drop_counter))
+            }));
+        }));
+        assert!(result.is_err());
+        // Everything that was pushed before the panic still belongs to the arena,
+        // and the item that was in flight was dropped.
+        assert_eq!(arena.len(), 3);
+        assert_eq!(drop_counter.get(), 1);
+    }
+    assert_eq!(drop_counter.get(), 4);
+}
+
+#[test]
+#[cfg(feature = "std")]
+fn sync_arena_in_static() {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::thread;
+
+    static DROPS: AtomicUsize = AtomicUsize::new(0);
+    static ARENA: SyncArena<Vec<usize>> = SyncArena::new();
+
+    struct CountDrops;
+    impl Drop for CountDrops {
+        fn drop(&mut self) {
+            DROPS.fetch_add(1, Ordering::SeqCst);
+        }
+    }
+
+    let handles: Vec<_> = (0..4)
+        .map(|i| {
+            thread::spawn(move || {
+                let mut refs = Vec::new();
+                for j in 0..100 {
+                    refs.push(ARENA.alloc(vec![i, j]));
+                }
+                let slice = ARENA.alloc_extend((0..10).map(|j| vec![i, j]));
+                // Nothing another thread allocated overwrote our values.
+                for (j, value) in refs.iter().enumerate() {
+                    assert_eq!(**value, [i, j]);
+                }
+                assert_eq!(slice.len(), 10);
+            })
+        })
+        .collect();
+    for handle in handles {
+        handle.join().unwrap();
+    }
+    assert_eq!(ARENA.len(), 440);
+
+    let arena = SyncArena::new();
+    for _ in 0..10 {
+        arena.alloc(CountDrops);
+    }
+    assert_eq!(arena.into_inner().len(), 10);
+    assert_eq!(DROPS.load(Ordering::SeqCst), 10);
+}
+
+#[test]
+fn inline_arena_spills_in_order() {
+    let drop_counter = Cell::new(0);
+    {
+        let arena: InlineArena<(u32, DropTracker), 4> = InlineArena::new();
+        arena.alloc((0, DropTracker(&drop_counter)));
+        // Fills the inline space exactly, without spilling.
+        let three = arena.alloc_extend((1..4).map(|i| (i, DropTracker(&drop_counter))));
+        assert_eq!(three.len(), 3);
+        assert!(!arena.spilled());
+        assert_eq!(chunk_capacities(&arena.spill), [0]);
+
+        // An iterator that claims to fit but doesn't.
+        let arena: InlineArena<(u32, DropTracker), 4> = InlineArena::new();
+        arena.alloc((0, DropTracker(&drop_counter)));
+        let rest = arena.alloc_extend(
+            (1..10)
+                .filter(|_| true)
+                .map(|i| (i, DropTracker(&drop_counter))),
+        );
+        assert_eq!(
+            rest.iter().map(|v| v.0).collect::<Vec<_>>(),
+            (1..10).collect::<Vec<_>>()
+        );
+        assert!(arena.spilled());
+        arena.alloc((10, DropTracker(&drop_counter)));
+        assert_eq!(arena.len(), 11);
+        assert_eq!(drop_counter.get(), 0);
+
+        let values: Vec<u32> = arena.into_vec().into_iter().map(|v| v.0).collect();
+        assert_eq!(values, (0..11).collect::<Vec<_>>());
+        assert_eq!(drop_counter.get(), 11);
+    }
+    assert_eq!(drop_counter.get(), 15);
+
+    let mut arena: InlineArena<u32, 0> = InlineArena::new();
+    arena.alloc(1);
+    arena.alloc_extend(2..4);
+    assert_eq!(arena.iter_mut().size_hint(), (3, Some(3)));
+    assert_eq!(arena.into_vec(), [1, 2, 3]);
+}
+
+#[test]
+fn buffer_arena() {
+    let drop_counter = Cell::new(0);
+    {
+        let mut buffer: [MaybeUninit<Node>; 4] = unsafe { MaybeUninit::uninit().assume_init() };
+        let arena = BufferArena::from_buffer(&mut buffer);
+        assert_eq!(arena.capacity(), 4);
+
+        // Values can reference each other, like in an `Arena`.
+        let root = arena
+            .alloc(Node(None, 0, DropTracker(&drop_counter)))
+            .ok()
+            .unwrap();
+        let child = arena
+            .alloc(Node(Some(root), 1, DropTracker(&drop_counter)))
+            .ok()
+            .unwrap();
+        assert_eq!(child.0.unwrap().1, 0);
+
+        // Doesn't fit, so nothing is kept.
+        let result = arena.alloc_extend(
+            (2..5)
+                .filter(|_| true)
+                .map(|i| Node(None, i, DropTracker(&drop_counter))),
+        );
+        assert_eq!(result.err(), Some(CapacityError));
+        assert_eq!(arena.len(), 2);
+        assert_eq!(drop_counter.get(), 3);
+
+        let pair = arena
+            .alloc_extend((2..4).map(|i| Node(None, i, DropTracker(&drop_counter))))
+            .ok()
+            .unwrap();
+        assert_eq!(pair.len(), 2);
+        let rejected = arena
+            .alloc(Node(None, 4, DropTracker(&drop_counter)))
+            .err()
+            .unwrap();
+        assert_eq!(rejected.1, 4);
+    }
+    assert_eq!(drop_counter.get(), 8);
+
+    let mut buffer = [MaybeUninit::uninit(); 8];
+    let arena = BufferArena::from_buffer(&mut buffer);
+    let result = panic::catch_unwind(AssertUnwindSafe(|| {
+        arena.alloc_extend((0..4).inspect(|&i| {
+            if i == 2 {
+                arena.alloc(100).unwrap();
+            }
+        }))
+    }));
+    assert!(result.is_err());
+    let mut arena = arena;
+    assert_eq!(
+        arena.iter_mut().map(|v| *v).collect::<Vec<_>>(),
+        [0, 1, 100]
+    );
+}
+
+#[test]
+fn locked_capacity_never_grows() {
+    let drop_counter = Cell::new(0);
+    {
+        let arena = Arena::with_capacity(4);
+        arena.alloc(Node(None, 0, DropTracker(&drop_counter)));
+        arena.lock_capacity();
+
+        let result = arena.try_alloc_extend(
+            (1..5)
+                .filter(|_| true)
+                .map(|i| Node(None, i, DropTracker(&drop_counter))),
+        );
+        assert_eq!(result.err(), Some(CapacityError));
+        assert_eq!(drop_counter.get(), 4);
+        assert_eq!(arena.len(), 1);
+
+        let pair = arena
+            .try_alloc_extend((1..3).map(|i| Node(None, i, DropTracker(&drop_counter))))
+            .ok()
+            .unwrap();
+        assert_eq!(pair.len(), 2);
+        assert!(arena
+            .try_alloc(Node(None, 3, DropTracker(&drop_counter)))
+            .is_ok());
+        let rejected = arena
+            .try_alloc(Node(None, 4, DropTracker(&drop_counter)))
+            .err()
+            .unwrap();
+        assert_eq!(rejected.1, 4);
+        drop(rejected);
+
+        let result = panic::catch_unwind(AssertUnwindSafe(|| {
+            arena.alloc(Node(None, 5, DropTracker(&drop_counter)));
+        }));
+        assert!(result.is_err());
+        assert_eq!(chunk_capacities(&arena), [4]);
+        assert_eq!(arena.len(), 4);
+    }
+    assert_eq!(drop_counter.get(), 10);
+
+    // Locked before anything was allocated, so there's no room at all.
+    let arena = Arena::new();
+    arena.lock_capacity();
+    assert_eq!(arena.try_alloc(1), Err(1));
+}
+
+#[test]
+fn memory_budget_is_shared_and_refunded() {
+    let budget = MemoryBudget::new(80 * 8);
+    let build = || -> Arena<u64> {
+        Arena::builder()
+            .initial_capacity(8)
+            .growth(Fixed(16))
+            .budget(budget.clone())
+            .build()
+    };
+
+    let mut a = build();
+    let b = build();
+    assert_eq!(budget.used(), 0);
+    a.alloc_extend(0..8);
+    a.alloc_extend(8..24);
+    assert_eq!(chunk_capacities(&a), [8, 16]);
+    assert_eq!(budget.used(), 24 * 8);
+    b.alloc_extend(0..40);
+    assert_eq!(budget.used(), 64 * 8);
+
+    // `b` gets one more chunk of 16, which uses up the budget.
+    b.alloc_extend(40..56);
+    assert_eq!(budget.remaining(), 0);
+    assert_eq!(b.try_alloc(56), Err(56));
+    assert_eq!(b.len(), 56);
+
+    // The values that didn't fit are rolled back, whether the chunk they were
+    // in would have been grown or replaced.
+    let drop_counter = Cell::new(0);
+    let c: Arena<DropTracker> = Arena::builder()
+        .initial_capacity(4)
+        .budget(MemoryBudget::new(4 * mem::size_of::<DropTracker>()))
+        .build();
+    let result = c.try_alloc_extend((0..5).filter(|_| true).map(|_| DropTracker(&drop_counter)));
+    assert_eq!(result.err(), Some(CapacityError));
+    assert_eq!(drop_counter.get(), 5);
+    assert_eq!(c.len(), 0);
+    c.alloc(DropTracker(&drop_counter));
+    let result = c.try_alloc_extend((0..4).filter(|_| true).map(|_| DropTracker(&drop_counter)));
+    assert!(result.is_err());
+    assert_eq!(drop_counter.get(), 9);
+    assert_eq!(c.len(), 1);
+    let result = panic::catch_unwind(AssertUnwindSafe(|| {
+        c.alloc_extend((0..4).map(|_| DropTracker(&drop_counter)));
+    }));
+    assert!(result.is_err());
+    assert_eq!(c.len(), 1);
+
+    // Clearing keeps the largest chunk, and dropping frees everything.
+    a.clear();
+    assert_eq!(a.len(), 0);
+    assert_eq!(chunk_capacities(&a), [16]);
+    assert_eq!(budget.used(), 72 * 8);
+    drop(b);
+    assert_eq!(budget.used(), 16 * 8);
+    drop(a);
+    assert_eq!(budget.used(), 0);
+}
+
+#[test]
+fn clear_drops_values_and_reuses_memory() {
+    let drop_counter = Cell::new(0);
+    let mut arena = Arena::builder().initial_capacity(2).backfill(true).build();
+    arena.alloc(DropTracker(&drop_counter));
+    arena.alloc_extend((0..5).map(|_| DropTracker(&drop_counter)));
+    arena.alloc(DropTracker(&drop_counter));
+    arena.clear();
+    assert_eq!(drop_counter.get(), 7);
+    assert_eq!(arena.len(), 0);
+    assert_eq!(wasted_tails(&arena), 0);
+    let largest = chunk_capacities(&arena);
+    assert_eq!(largest.len(), 1);
+
+    for _ in 0..largest[0] {
+        arena.alloc(DropTracker(&drop_counter));
+    }
+    assert_eq!(chunk_capacities(&arena), largest);
+    assert_eq!(arena.iter_mut().count(), largest[0]);
+
+    let mut empty: Arena<u8> = Arena::new();
+    empty.clear();
+    assert_eq!(chunk_capacities(&empty), [0]);
+}
+
+#[derive(Default)]
+struct CountingAlloc {
+    live: Cell<usize>,
+    allocations: Cell<usize>,
+}
+
+unsafe impl allocator_api2::alloc::Allocator for &CountingAlloc {
+    fn allocate(
+        &self,
+        layout: allocator_api2::alloc::Layout,
+    ) -> Result<ptr::NonNull<[u8]>, allocator_api2::alloc::AllocError> {
+        self.live.set(self.live.get() + 1);
+        self.allocations.set(self.allocations.get() + 1);
+        Global.allocate(layout)
+    }
+
+    unsafe fn deallocate(&self, ptr: ptr::NonNull<u8>, layout: allocator_api2::alloc::Layout) {
+        self.live.set(self.live.get() - 1);
+        Global.deallocate(ptr, layout)
+    }
+}
+
+#[test]
+fn chunks_come_from_the_allocator() {
+    let counter = CountingAlloc::default();
+    {
+        let arena = Arena::new_in(&counter);
+        assert_eq!(counter.allocations.get(), 0);
+        arena.alloc(1u64);
+        assert_eq!(counter.live.get(), 1);
+        arena.alloc_extend(0..1000);
+        assert!(counter.live.get() > 1);
+        assert_eq!(arena.len(), 1001);
+    }
+    assert_eq!(counter.live.get(), 0);
+
+    let mut arena = Arena::builder().initial_capacity(4).build_in(&counter);
+    arena.alloc_extend(vec![String::from("a"); 10]);
+    arena.clear();
+    assert_eq!(counter.live.get(), 1);
+    let allocations = counter.allocations.get();
+    assert_eq!(arena.into_vec(), Vec::<String>::new());
+    assert_eq!(counter.live.get(), 0);
+    assert_eq!(counter.allocations.get(), allocations);
+}
+
+#[test]
+#[cfg(feature = "std")]
+fn chunk_pool_reuses_chunks_between_arenas() {
+    let pool = ChunkPool::new(1024 * 1024);
+    let first = {
+        let arena: Arena<u64> = Arena::builder().chunk_pool(pool.clone()).build();
+        arena.alloc_extend(0..1000);
+        arena.alloc(1) as *mut u64
+    };
+    let retained = pool.retained_chunks();
+    assert!(retained > 1);
+
+    let arena: Arena<u64> = Arena::builder().chunk_pool(pool.clone()).build();
+    arena.alloc(1);
+    assert_eq!(pool.retained_chunks(), retained - 1);
+    arena.alloc_extend(0..999);
+    assert_eq!(arena.alloc(1) as *mut u64, first);
+    assert_eq!(pool.retained_chunks(), 0);
+    drop(arena);
+    assert_eq!(pool.retained_chunks(), retained);
+
+    pool.clear();
+    assert_eq!(pool.retained_bytes(), 0);
+}
+
+#[test]
+#[cfg(feature = "std")]
+fn chunk_pool_limits_what_it_retains() {
+    let pool = ChunkPool::new(100 * 8);
+    let budget = MemoryBudget::new(usize::MAX);
+    let mut arena: Arena<u64> = Arena::builder()
+        .initial_capacity(32)
+        .chunk_pool(pool.clone())
+        .budget(budget.clone())
+        .build();
+    for i in 0..32 + 64 + 128 {
+        arena.alloc(i);
+    }
+    arena.clear();
+    assert_eq!(budget.used(), 128 * 8);
+    assert_eq!(pool.retained_bytes(), (32 + 64) * 8);
+
+    drop(arena);
+    assert_eq!(budget.used(), 0);
+    assert_eq!(pool.retained_chunks(), 2);
+
+    // Chunks bigger than the arena's limit aren't handed out.
+    let arena: Arena<u64> = Arena::builder()
+        .initial_capacity(16)
+        .max_chunk_capacity(32)
+        .chunk_pool(pool.clone())
+        .build();
+    for i in 0..16 + 32 {
+        arena.alloc(i);
+    }
+    assert_eq!(chunk_capacities(&arena), [32, 32]);
+    assert_eq!(pool.retained_chunks(), 1);
+}
+
+#[test]
+#[cfg(feature = "std")]
+fn arena_pool_keeps_arenas_sized_for_the_peak() {
+    let pool: ArenaPool<u32> = ArenaPool::new(2);
+    let a = pool.get();
+    let b = pool.get();
+    a.alloc_extend(0..1000);
+    b.alloc(1);
+    drop(a);
+    assert_eq!(pool.peak_len(), 1000);
+    assert_eq!(pool.warm_arenas(), 1);
+    // `b` is too small for the peak.
+    drop(b);
+    assert_eq!(pool.warm_arenas(), 1);
+
+    let warm = pool.get();
+    let fresh = pool.get();
+    assert_eq!(chunk_capacities(&warm), [1000]);
+    assert_eq!(chunk_capacities(&fresh), [pool.peak_len()]);
+    drop((warm, fresh));
+    assert_eq!(pool.warm_arenas(), 2);
+
+    // Once requests get smaller, big arenas are replaced by smaller ones.
+    for _ in 0..100 {
+        pool.get().alloc(1);
+    }
+    assert_eq!(pool.peak_len(), 1);
+    let arena = pool.get();
+    assert!(chunk_capacities(&arena)[0] <= 4);
+}
+
+#[test]
+#[cfg(feature = "std")]
+fn arena_pool_unlocks_returned_arenas() {
+    let pool: ArenaPool<u32> = ArenaPool::new(1);
+    let arena = pool.get();
+    arena.alloc_extend(0..100);
+    arena.lock_capacity();
+    drop(arena);
+    assert_eq!(pool.warm_arenas(), 1);
+
+    let arena = pool.get();
+    for i in 0..10_000 {
+        assert_eq!(arena.try_alloc(i), Ok(&mut { i }));
+    }
+}
+
+#[test]
+fn shrink_to_fit_and_compact() {
+    let drop_counter = Cell::new(0);
+    let budget = MemoryBudget::new(usize::MAX);
+    let mut arena = Arena::builder()
+        .initial_capacity(4)
+        .backfill(true)
+        .budget(budget.clone())
+        .build();
+    for i in 0..10 {
+        arena.alloc((i, DropTracker(&drop_counter)));
+    }
+    arena.alloc_extend((10..30).map(|i| (i, DropTracker(&drop_counter))));
+    let size = mem::size_of::<(i32, DropTracker)>();
+    assert_eq!(chunk_capacities(&arena), [4, 8, 32]);
+
+    let freed = arena.shrink_to_fit();
+    assert_eq!(freed, (4 + 8 + 32 - 30) * size);
+    assert_eq!(chunk_capacities(&arena), [4, 6, 20]);
+    assert_eq!(budget.used(), 30 * size);
+    assert_eq!(arena.shrink_to_fit(), 0);
+
+    // The chunks are full, so there is nothing to gain from moving values.
+    assert_eq!(arena.compact(), 0);
+    assert_eq!(chunk_capacities(&arena), [4, 6, 20]);
+    arena.alloc((30, DropTracker(&drop_counter)));
+    assert_eq!(drop_counter.get(), 0);
+
+    let mut values: Vec<i32> = arena.iter_mut().map(|&mut (i, _)| i).collect();
+    values.sort_unstable();
+    assert_eq!(values, (0..31).collect::<Vec<_>>());
+    drop(values);
+    drop(arena);
+    assert_eq!(drop_counter.get(), 31);
+    assert_eq!(budget.used(), 0);
+
+    let mut empty: Arena<u64> = Arena::with_capacity(16);
+    assert_eq!(empty.compact(), 16 * 8);
+    assert_eq!(chunk_capacities(&empty), [0]);
+    empty.alloc(1);
+}
+
+#[test]
+fn shrink_to_fit_and_compact_leave_locked_arenas_alone() {
+    let mut arena = Arena::with_capacity(4);
+    for i in 0..20 {
+        arena.alloc(i);
+    }
+    arena.lock_capacity();
+    let stats = arena.stats();
+    assert_eq!(arena.compact(), 0);
+    assert_eq!(arena.shrink_to_fit(), 0);
+    assert_eq!(arena.stats(), stats);
+
+    // The spare capacity of the current chunk is still there.
+    let spare = stats.capacity - stats.len;
+    for i in 0..spare {
+        assert!(arena.try_alloc(i).is_ok());
+    }
+    assert!(arena.try_alloc(0).is_err());
+}
+
+#[test]
+#[cfg(feature = "std")]
+fn shrink_to_fit_returns_chunks_to_the_pool() {
+    let pool = ChunkPool::new(usize::MAX);
+    let mut arena: Arena<u64> = Arena::builder()
+        .initial_capacity(64)
+        .chunk_pool(pool.clone())
+        .build();
+    arena.alloc_extend(0..10);
+    assert_eq!(arena.shrink_to_fit(), 54 * 8);
+    assert_eq!(pool.retained_bytes(), 64 * 8);
+    assert_eq!(arena.into_vec(), (0..10).collect::<Vec<_>>());
+    assert_eq!(pool.retained_bytes(), 74 * 8);
+}
+
+#[test]
+#[cfg(feature = "std")]
+fn compact_takes_an_exact_chunk_from_the_pool() {
+    let pool = ChunkPool::new(usize::MAX);
+    for &capacity in &[4, 8, 10_000] {
+        let arena: Arena<u64> = Arena::builder()
+            .initial_capacity(capacity)
+            .chunk_pool(pool.clone())
+            .build();
+        arena.alloc(0);
+    }
+    assert_eq!(pool.retained_bytes(), 10_012 * 8);
+
+    let mut arena: Arena<u64> = Arena::builder()
+        .initial_capacity(4)
+        .chunk_pool(pool.clone())
+        .build();
+    arena.alloc_extend(0..4);
+    arena.alloc_extend(4..10);
+    assert_eq!(chunk_capacities(&arena), [4, 8]);
+    assert_eq!(arena.compact(), 2 * 8);
+    assert_eq!(chunk_capacities(&arena), [10]);
+    assert_eq!(arena.into_vec(), (0..10).collect::<Vec<_>>());
+}
+
+#[test]
+fn stats_and_debug() {
+    let arena: Arena<u64> = Arena::new();
+    assert_eq!(arena.stats(), ArenaStats::default());
+    assert_eq!(
+        format!("{:?}", arena),
+        "Arena { len: 0, capacity: 0, chunks: 0, allocated_bytes: 0, wasted_bytes: 0, \
+         largest_chunk_capacity: 0 }"
+    );
+
+    let arena = Arena::builder().initial_capacity(4).build();
+    arena.alloc_extend(0..3);
+    arena.alloc_extend(0..6);
+    arena.alloc(0u64);
+    let stats = arena.stats();
+    assert_eq!(stats.len, 10);
+    assert_eq!(stats.capacity, 4 + 8);
+    assert_eq!(stats.chunks, 2);
+    assert_eq!(stats.allocated_bytes, 12 * 8);
+    assert_eq!(stats.wasted_bytes, 8);
+    assert_eq!(stats.largest_chunk_capacity, 8);
+
+    let units = Arena::new();
+    units.alloc_extend(vec![(); 10]);
+    let stats = units.stats();
+    assert_eq!(stats.len, 10);
+    assert_eq!(stats.allocated_bytes, 0);
+}
+
+#[test]
+fn observer_sees_chunks_extends_and_drop() {
+    use std::sync::{Arc, Mutex};
+
+    #[derive(Default)]
+    struct Events(Mutex<Vec<String>>);
+    struct Recorder(Arc<Events>);
+    impl ArenaObserver for Recorder {
+        fn chunk_allocated(&self, name: Option<&str>, bytes: usize) {
+            let event = format!("{:?} chunk {}", name, bytes);
+            self.0 .0.lock().unwrap().push(event);
+        }
+        fn large_extend(&self, name: Option<&str>, bytes: usize) {
+            let event = format!("{:?} extend {}", name, bytes);
+            self.0 .0.lock().unwrap().push(event);
+        }
+        fn large_extend_bytes(&self) -> usize {
+            100
+        }
+        fn dropped(&self, name: Option<&str>, stats: &ArenaStats) {
+            let event = format!("{:?} dropped {}", name, stats.len);
+            self.0 .0.lock().unwrap().push(event);
+        }
+    }
+
+    let events = Arc::new(Events::default());
+    let arena: Arena<u32> = Arena::builder()
+        .name("test")
+        .initial_capacity(8)
+        .observer(Recorder(events.clone()))
+        .build();
+    arena.alloc_extend(0..4);
+    arena.alloc_extend(0..30);
+    drop(arena);
+    assert_eq!(
+        *events.0.lock().unwrap(),
+        [
+            "Some(\"test\") chunk 32",
+            "Some(\"test\") chunk 128",
+            "Some(\"test\") extend 120",
+            "Some(\"test\") dropped 34",
+        ]
+    );
+
+    let events = Arc::new(Events::default());
+    let arena = Arena::builder().observer(Recorder(events.clone())).build();
+    arena.alloc(0u8);
+    drop(arena);
+    assert_eq!(
+        *events.0.lock().unwrap(),
+        ["None chunk 1024", "None dropped 1"]
+    );
+}
+
+#[test]
+fn observer_sees_segmented_and_uninitialized_extends() {
+    use std::sync::{Arc, Mutex};
+
+    struct Recorder(Arc<Mutex<Vec<usize>>>);
+    impl ArenaObserver for Recorder {
+        fn large_extend(&self, _name: Option<&str>, bytes: usize) {
+            self.0.lock().unwrap().push(bytes);
+        }
+        fn large_extend_bytes(&self) -> usize {
+            100
+        }
+    }
+
+    let extends = Arc::new(Mutex::new(Vec::new()));
+    let arena: Arena<u32> = Arena::builder()
+        .initial_capacity(8)
+        .observer(Recorder(extends.clone()))
+        .build();
+    arena.alloc_extend_segmented((0..30).filter(|_| true));
+    arena.alloc_extend_segmented(0..4);
+    unsafe {
+        for slot in arena.alloc_uninitialized(40) {
+            slot.as_mut_ptr().write(0);
+        }
+        arena.alloc_uninitialized(0);
+    }
+    assert_eq!(*extends.lock().unwrap(), [120, 160]);
+}
+
+#[test]
+#[cfg(feature = "debug-callsites")]
+fn callsites_are_attributed_to_the_caller() {
+    let arena: Arena<u8> = Arena::new();
+    let line = line!() + 2;
+    for _ in 0..3 {
+        arena.alloc_str("hello");
+    }
+    arena.alloc(0);
+    let _ = arena.try_alloc_extend(vec![1, 2]);
+
+    let report = arena.callsite_report();
+    assert_eq!(report.len(), 3);
+    assert_eq!(report[0].location.file(), file!());
+    assert_eq!(report[0].location.line(), line);
+    assert_eq!(
+        (report[0].calls, report[0].values, report[0].bytes),
+        (3, 15, 15)
+    );
+    assert_eq!((report[1].calls, report[1].bytes), (1, 2));
+    assert_eq!((report[2].calls, report[2].bytes), (1, 1));
+
+    let arena = SyncArena::new();
+    arena.alloc(0u64);
+    let report = arena.into_inner().callsite_report();
+    assert_eq!(report[0].location.file(), file!());
+
+    // Inline and spilled values are reported together.
+    let arena = InlineArena::<u8, 4>::new();
+    let line = line!() + 2;
+    for i in 0..3 {
+        arena.alloc(i);
+    }
+    arena.alloc_extend(vec![3]);
+    arena.alloc_extend(vec![4, 5]);
+    arena.alloc(6);
+    assert!(arena.spilled());
+    let report = arena.callsite_report();
+    assert_eq!(report.len(), 4);
+    assert_eq!(report[0].location.file(), file!());
+    assert_eq!(report[0].location.line(), line);
+    assert_eq!((report[0].calls, report[0].values), (3, 3));
+    let lines: Vec<u32> = report[1..]
+        .iter()
+        .map(|stats| stats.location.line())
+        .collect();
+    assert_eq!(lines, [line + 3, line + 2, line + 4]);
+}
+
+#[test]
+fn ids_find_their_values() {
+    let mut arena = Arena::with_capacity(2);
+    let ids: Vec<Id<String>> = (0..20).map(|i| arena.alloc_id(i.to_string())).collect();
+    arena.alloc_extend(vec![String::from("x"); 5]);
+    let last = arena.alloc_id(String::from("last"));
+    assert_eq!(last.index(), 25);
+
+    for (i, &id) in ids.iter().enumerate() {
+        assert_eq!(id.index(), i);
+        assert_eq!(arena.get(id), Some(&i.to_string()));
+    }
+    arena.get_mut(last).unwrap().push('!');
+    arena.compact();
+    assert_eq!(arena.get(last).unwrap(), "last!");
+    assert_eq!(arena.get(ids[7]).unwrap(), "7");
+
+    let other = Arena::new();
+    other.alloc_id(String::new());
+    assert_eq!(other.get(ids[0]), None);
+
+    arena.clear();
+    assert_eq!(arena.get(ids[0]), None);
+    let id = arena.alloc_id(String::from("new"));
+    assert_eq!(id.index(), ids[0].index());
+    assert_ne!(id, ids[0]);
+    assert_eq!(arena.get(ids[0]), None);
+    assert_eq!(arena.get(id).unwrap(), "new");
+
+    let units = Arena::new();
+    let unit = units.alloc_id(());
+    assert_eq!(units.get(unit), Some(&()));
+}
+
+#[test]
+#[should_panic(expected = "ids can't be used with an arena that backfills")]
+fn ids_need_allocation_order() {
+    let arena = Arena::builder().backfill(true).build();
+    arena.alloc_id(0);
+}
+
+#[test]
+fn generations_change_on_clear() {
+    let mut arena = Arena::new();
+    assert_eq!(arena.generation(), 0);
+    arena.clear();
+    assert_eq!(arena.generation(), 0);
+
+    let first = arena.alloc_id(1);
+    assert_eq!(first.generation(), arena.generation());
+    arena.shrink_to_fit();
+    assert_eq!(arena.get(first), Some(&1));
+
+    let mut generations = vec![arena.generation()];
+    for i in 0..3 {
+        arena.clear();
+        assert_eq!(arena.generation(), 0);
+        assert_eq!(arena.get(first), None);
+        let id = arena.alloc_id(i);
+        assert!(!generations.contains(&arena.generation()));
+        generations.push(arena.generation());
+        assert_eq!(id.index(), first.index());
+        assert_eq!(arena.get(first), None);
+        assert_eq!(arena.get_mut(id).copied(), Some(i));
+    }
+
+    // Clearing an arena that hands out no new ids doesn't draw generations.
+    for _ in 0..10 {
+        arena.alloc(0);
+        arena.clear();
+    }
+    assert_eq!(arena.generation(), 0);
+}
+
+#[test]
+fn branded_ids() {
+    let drop_counter = Cell::new(0);
+    let arena = Arena::with_brand(|mut arena| {
+        let mut ids = Vec::new();
+        for i in 0..100 {
+            arena.alloc((i, DropTracker(&drop_counter)));
+            ids.push(arena.alloc_id((i, DropTracker(&drop_counter))));
+        }
+        arena.alloc_extend((0..10).map(|i| (i, DropTracker(&drop_counter))));
+        for (i, &id) in ids.iter().enumerate() {
+            assert_eq!(id.index(), 2 * i + 1);
+            assert_eq!(arena.get(id).0, i);
+        }
+        arena.get_mut(ids[3]).0 = 1000;
+        assert_eq!(arena.get(ids[3]).0, 1000);
+        assert_eq!(arena.len(), 210);
+        arena.into_inner()
+    });
+    assert_eq!(drop_counter.get(), 0);
+    drop(arena);
+    assert_eq!(drop_counter.get(), 210);
+}
+
+#[test]
+// `ArenaRef` hashes the address, so interior mutability in the value is fine.
+#[allow(clippy::mutable_key_type)]
+fn arena_refs_compare_by_address() {
+    use std::collections::{BTreeMap, HashMap};
+
+    struct Node<'a> {
+        edges: RefCell<Vec<ArenaRef<'a, Node<'a>>>>,
+    }
+
+    let arena = Arena::new();
+    let nodes: Vec<ArenaRef<Node>> = (0..4)
+        .map(|_| {
+            ArenaRef::from(arena.alloc(Node {
+                edges: RefCell::new(Vec::new()),
+            }))
+        })
+        .collect();
+    for (i, node) in nodes.iter().enumerate() {
+        node.edges.borrow_mut().push(nodes[(i + 1) % 4]);
+    }
+
+    let mut indices = HashMap::new();
+    for (i, &node) in nodes.iter().enumerate() {
+        indices.insert(node, i);
+    }
+    for (i, node) in nodes.iter().enumerate() {
+        let next = node.edges.borrow()[0];
+        assert_eq!(indices[&next], (i + 1) % 4);
+    }
+
+    let ordered: BTreeMap<_, _> = nodes.iter().map(|&node| (node, ())).collect();
+    assert_eq!(ordered.len(), 4);
+    assert!(nodes[0] < nodes[1]);
+
+    let strings: Arena<u8> = Arena::new();
+    let a = ArenaRef::from(&*strings.alloc_str("abc"));
+    let b = ArenaRef::from(&*strings.alloc_str("abc"));
+    assert_eq!(&*a, &*b);
+    assert_ne!(a, b);
+}
+
+#[test]
+fn owned_arenas_keep_their_root() {
+    use std::sync::{Arc, Mutex};
+    use std::thread;
+
+    struct Leaf {
+        name: String,
+        log: Arc<Mutex<Vec<String>>>,
+    }
+
+    impl Drop for Leaf {
+        fn drop(&mut self) {
+            self.log.lock().unwrap().push(format!("leaf {}", self.name));
+        }
+    }
+
+    struct Tree<'a> {
+        leaves: Vec<&'a Leaf>,
+    }
+
+    impl<'a> Drop for Tree<'a> {
+        fn drop(&mut self) {
+            // The leaves are still alive when the root is dropped.
+            let first = &self.leaves[0];
+            first
+                .log
+                .lock()
+                .unwrap()
+                .push(format!("tree {}", first.name));
+        }
+    }
+
+    struct TreeRoot;
+
+    unsafe impl<'a> ArenaRoot<'a> for TreeRoot {
+        type Elem = Leaf;
+        type Of = Tree<'a>;
+    }
+
+    fn build(log: &Arc<Mutex<Vec<String>>>) -> OwnedArena<TreeRoot> {
+        OwnedArena::new(Arena::new(), |arena| Tree {
+            leaves: ["a", "b"]
+                .iter()
+                .map(|&name| {
+                    &*arena.alloc(Leaf {
+                        name: name.to_string(),
+                        log: log.clone(),
+                    })
+                })
+                .collect(),
+        })
+    }
+
+    let log = Arc::new(Mutex::new(Vec::new()));
+    let mut tree = build(&log);
+    tree.with_root_mut(|arena, tree| {
+        tree.leaves.push(arena.alloc(Leaf {
+            name: "c".to_string(),
+            log: log.clone(),
+        }))
+    });
+    assert_eq!(tree.arena().len(), 3);
+
+    let tree = thread::spawn(move || {
+        tree.with_root(|tree| {
+            tree.leaves
+                .iter()
+                .map(|leaf| leaf.name.len())
+                .sum::<usize>()
+        })
+    })
+    .join()
+    .unwrap();
+    assert_eq!(tree, 3);
+    assert_eq!(
+        *log.lock().unwrap(),
+        ["tree a", "leaf a", "leaf b", "leaf c"]
+    );
+
+    let arena = build(&log).into_arena();
+    assert_eq!(arena.len(), 2);
+    assert_eq!(log.lock().unwrap().len(), 5);
+}
+
+#[test]
+fn owned_arenas_hold_graphs() {
+    struct Node<'a> {
+        value: usize,
+        next: Cell<Option<&'a Node<'a>>>,
+        children: Vec<&'a Node<'a>>,
+    }
+
+    struct Ring;
+
+    unsafe impl<'a> ArenaRoot<'a> for Ring {
+        type Elem = Node<'a>;
+        type Of = &'a Node<'a>;
+    }
+
+    fn ring(len: usize) -> OwnedArena<Ring> {
+        OwnedArena::new(Arena::with_capacity(2), |arena| {
+            let first = arena.alloc(Node {
+                value: 0,
+                next: Cell::new(None),
+                children: Vec::new(),
+            });
+            let mut last = &*first;
+            for value in 1..len {
+                let node = arena.alloc(Node {
+                    value,
+                    next: Cell::new(None),
+                    children: vec![first, last],
+                });
+                last.next.set(Some(node));
+                last = node;
+            }
+            last.next.set(Some(first));
+            &*first
+        })
+    }
+
+    fn sum(first: &Node) -> usize {
+        let mut node = first;
+        let mut sum = 0;
+        loop {
+            sum += node.value;
+            node = node.next.get().unwrap();
+            if std::ptr::eq(node, first) {
+                return sum;
+            }
+        }
+    }
+
+    let mut ring = ring(10);
+    assert_eq!(ring.with_root(|first| sum(first)), 45);
+    ring.with_root_mut(|arena, first| {
+        let node = arena.alloc(Node {
+            value: 10,
+            next: Cell::new(first.next.get()),
+            children: vec![*first],
+        });
+        first.next.set(Some(node));
+        *first = node;
+    });
+    assert_eq!(ring.with_root(|first| first.value), 10);
+    assert_eq!(ring.with_root(|first| first.children[0].value), 0);
+    assert_eq!(ring.with_root(|first| sum(first)), 55);
+    assert_eq!(ring.with_arena(|arena| arena.len()), 11);
+}
+
+#[test]
+fn rc_arenas_live_as_long_as_their_handles() {
+    let drop_counter = Cell::new(0);
+    let first = {
+        let arena = RcArena::new();
+        let first = arena.alloc(DropTracker(&drop_counter));
+        arena.alloc(DropTracker(&drop_counter));
+        first
+    };
+    assert_eq!(drop_counter.get(), 0);
+
+    let second = ArenaRc::arena(&first).alloc(DropTracker(&drop_counter));
+    assert!(!ArenaRc::ptr_eq(&first, &second));
+    assert!(ArenaRc::ptr_eq(&first, &first.clone()));
+    drop(first);
+    assert_eq!(drop_counter.get(), 0);
+    drop(second);
+    assert_eq!(drop_counter.get(), 3);
+}
+
+#[test]
+#[cfg(feature = "std")]
+fn arc_arenas_can_be_shared_between_threads() {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+    use std::thread;
+
+    struct Counted(Arc<AtomicUsize>);
+
+    impl Drop for Counted {
+        fn drop(&mut self) {
+            self.0.fetch_add(1, Ordering::SeqCst);
+        }
+    }
+
+    let drops = Arc::new(AtomicUsize::new(0));
+    let arena = ArcArena::new();
+    let handles: Vec<_> = (0..4)
+        .map(|_| {
+            let arena = arena.clone();
+            let drops = drops.clone();
+            thread::spawn(move || arena.alloc(Counted(drops)))
+        })
+        .collect();
+    drop(arena);
+    let values: Vec<_> = handles.into_iter().map(|h| h.join().unwrap()).collect();
+    assert_eq!(ArenaArc::arena(&values[0]).arena().len(), 4);
+
+    thread::spawn(move || drop(values)).join().unwrap();
+    assert_eq!(drops.load(Ordering::SeqCst), 4);
+}
+
+#[test]
+// Miri reports the leaked arena as a leak, which is the point.
+#[cfg_attr(miri, ignore)]
+fn leaked_arenas_never_drop_their_values() {
+    use std::rc::Rc;
+
+    struct Counted(Rc<Cell<u32>>);
+
+    impl Drop for Counted {
+        fn drop(&mut self) {
+            self.0.set(self.0.get() + 1);
+        }
+    }
+
+    let drops = Rc::new(Cell::new(0));
+    let arena: &'static Arena<Counted> = Arena::with_capacity(4).leak();
+    let value: &'static mut Counted = arena.alloc(Counted(drops.clone()));
+    arena.alloc(Counted(drops.clone()));
+    assert_eq!(Rc::strong_count(&value.0), 3);
+
+    let stats = arena.stats();
+    assert_eq!(stats.len, 2);
+    assert_eq!(stats.capacity, 4);
+    assert_eq!(drops.get(), 0);
+}
+
+#[test]
+fn pinned_arenas_initialize_in_place() {
+    use std::marker::PhantomPinned;
+    use std::mem::MaybeUninit;
+    use std::panic::{self, AssertUnwindSafe};
+    use std::pin::Pin;
+
+    struct Pinned<'a> {
+        this: *const Pinned<'a>,
+        _tracker: DropTracker<'a>,
+        _pin: PhantomPinned,
+    }
+
+    unsafe fn init<'a>(slot: Pin<&mut MaybeUninit<Pinned<'a>>>, drop_counter: &'a Cell<u32>) {
+        let slot = slot.get_unchecked_mut().as_mut_ptr();
+        ptr::write(
+            slot,
+            Pinned {
+                this: slot,
+                _tracker: DropTracker(drop_counter),
+                _pin: PhantomPinned,
+            },
+        );
+    }
+
+    let drop_counter = Cell::new(0);
+    {
+        let arena = PinnedArena::with_capacity(4);
+        let first = unsafe { arena.alloc_with(|slot| init(slot, &drop_counter)) };
+        assert!(ptr::eq(first.this, &*first));
+
+        let failed = panic::catch_unwind(AssertUnwindSafe(|| unsafe {
+            arena.alloc_with(|_| panic!("init failed"));
+        }));
+        assert!(failed.is_err());
+        assert_eq!(arena.len(), 1);
+
+        let reentered = panic::catch_unwind(AssertUnwindSafe(|| unsafe {
+            arena.alloc_with(|_| {
+                arena.alloc(Pinned {
+                    this: ptr::null(),
+                    _tracker: DropTracker(&drop_counter),
+                    _pin: PhantomPinned,
+                });
+            });
+        }));
+        assert!(reentered.is_err());
+        assert_eq!(arena.len(), 1);
+        assert_eq!(drop_counter.get(), 1);
+
+        let second = unsafe { arena.alloc_with(|slot| init(slot, &drop_counter)) };
+        assert!(ptr::eq(second.this, &*second));
+        assert_eq!(
+            second.this as usize - first.this as usize,
+            mem::size_of::<Pinned>()
+        );
+        assert_eq!(arena.len(), 2);
+    }
+    assert_eq!(drop_counter.get(), 3);
+}